@@ -0,0 +1,14 @@
+//! Print the registry manifest (categories, tools, ports, headers) as JSON
+//!
+//! Usage: `cargo run --example export_manifest > kodegen-manifest.json`
+
+fn main() {
+    let manifest = kodegen_config::registry_manifest();
+    match serde_json::to_string_pretty(&manifest) {
+        Ok(json) => println!("{}", json),
+        Err(e) => {
+            eprintln!("Failed to serialize manifest: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
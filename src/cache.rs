@@ -0,0 +1,334 @@
+//! Cache size accounting and garbage collection
+//!
+//! `cache_dir()` is split into per-namespace subdirectories (`chrome/`,
+//! `builds/`, `downloads/`, ...), obtained via
+//! [`cache_namespace_dir()`](crate::KodegenConfig::cache_namespace_dir).
+//! Each top-level entry inside a namespace (a file or a directory) is the unit
+//! of accounting and eviction; its last use is the most recent access or
+//! modification time of anything inside it.
+//!
+//! [`cache_gc()`](crate::KodegenConfig::cache_gc) first evicts entries older
+//! than [`CachePolicy::max_age`], then evicts least recently used entries
+//! until the cache fits in [`CachePolicy::max_bytes`].
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Default cache quota (10 GiB)
+pub const DEFAULT_CACHE_QUOTA: u64 = 10 * 1024 * 1024 * 1024;
+
+/// Default maximum age of an unused cache entry (30 days)
+pub const DEFAULT_CACHE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Eviction policy for [`cache_gc()`](crate::KodegenConfig::cache_gc)
+#[derive(Debug, Clone)]
+pub struct CachePolicy {
+    /// Total size the cache is reduced to (`None` for no quota)
+    pub max_bytes: Option<u64>,
+    /// Entries unused for longer than this are evicted (`None` for no limit)
+    pub max_age: Option<Duration>,
+    /// Only report what would be evicted
+    pub dry_run: bool,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self { max_bytes: Some(DEFAULT_CACHE_QUOTA), max_age: Some(DEFAULT_CACHE_MAX_AGE), dry_run: false }
+    }
+}
+
+/// Why an entry was evicted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionReason {
+    /// Unused for longer than [`CachePolicy::max_age`]
+    Expired,
+    /// Least recently used while the cache exceeded [`CachePolicy::max_bytes`]
+    OverQuota,
+}
+
+/// A top-level entry of a cache namespace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    /// Namespace (subdirectory of `cache_dir()`); empty for files directly in `cache_dir()`
+    pub namespace: String,
+    /// File or directory
+    pub path: PathBuf,
+    /// Total size in bytes
+    pub bytes: u64,
+    /// Most recent access or modification inside the entry
+    pub last_used: SystemTime,
+}
+
+/// Size of one cache namespace
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NamespaceUsage {
+    /// Namespace name
+    pub namespace: String,
+    /// Total size in bytes
+    pub bytes: u64,
+    /// Number of top-level entries
+    pub entries: usize,
+}
+
+/// Result of [`cache_gc()`](crate::KodegenConfig::cache_gc)
+#[derive(Debug, Clone, Default)]
+pub struct CacheReport {
+    /// Whether this was a dry-run
+    pub dry_run: bool,
+    /// Usage per namespace before eviction, sorted by name
+    pub usage: Vec<NamespaceUsage>,
+    /// Entries evicted (or that would be), oldest first
+    pub evicted: Vec<(CacheEntry, EvictionReason)>,
+    /// Entries that could not be removed, with the error
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl CacheReport {
+    /// Total cache size before eviction
+    pub fn total_bytes(&self) -> u64 {
+        self.usage.iter().map(|u| u.bytes).sum()
+    }
+
+    /// Bytes freed (or that would be)
+    pub fn freed_bytes(&self) -> u64 {
+        self.evicted.iter().map(|(entry, _)| entry.bytes).sum()
+    }
+}
+
+impl std::fmt::Display for CacheReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for usage in &self.usage {
+            let name = if usage.namespace.is_empty() { "(root)" } else { &usage.namespace };
+            writeln!(f, "{:<16} {:>12} bytes in {} entries", name, usage.bytes, usage.entries)?;
+        }
+        let verb = if self.dry_run { "would evict" } else { "evicted" };
+        for (entry, reason) in &self.evicted {
+            let reason = match reason {
+                EvictionReason::Expired => "expired",
+                EvictionReason::OverQuota => "over quota",
+            };
+            writeln!(f, "{} {} ({} bytes, {})", verb, entry.path.display(), entry.bytes, reason)?;
+        }
+        for (path, error) in &self.failed {
+            writeln!(f, "FAILED to evict {}: {}", path.display(), error)?;
+        }
+        write!(f, "{} {} of {} bytes", verb, self.freed_bytes(), self.total_bytes())
+    }
+}
+
+/// Get (and create) a namespace subdirectory of `cache_dir`
+pub(crate) fn namespace_dir(cache_dir: &Path, namespace: &str) -> Result<PathBuf> {
+    crate::validation::validate_name(namespace)
+        .map_err(|e| anyhow::anyhow!("Invalid cache namespace: {}", e))?;
+    let dir = cache_dir.join(namespace);
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create cache directory: {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// Scan the cache and evict entries according to `policy`
+pub(crate) fn gc(cache_dir: &Path, policy: &CachePolicy, now: SystemTime) -> Result<CacheReport> {
+    let mut entries = scan(cache_dir)?;
+    let mut report = CacheReport { dry_run: policy.dry_run, usage: usage(&entries), ..Default::default() };
+
+    // Least recently used first
+    entries.sort_by_key(|entry| entry.last_used);
+
+    let mut remaining = report.total_bytes();
+    for entry in entries {
+        let expired = policy
+            .max_age
+            .is_some_and(|max_age| now.duration_since(entry.last_used).unwrap_or_default() > max_age);
+        let over_quota = policy.max_bytes.is_some_and(|max_bytes| remaining > max_bytes);
+
+        let reason = match (expired, over_quota) {
+            (true, _) => EvictionReason::Expired,
+            (false, true) => EvictionReason::OverQuota,
+            (false, false) => continue,
+        };
+
+        if !policy.dry_run
+            && let Err(e) = remove(&entry.path)
+        {
+            report.failed.push((entry.path.clone(), e.to_string()));
+            continue;
+        }
+        remaining = remaining.saturating_sub(entry.bytes);
+        report.evicted.push((entry, reason));
+    }
+
+    if !policy.dry_run && !report.evicted.is_empty() {
+        log::info!("Cache GC freed {} bytes from {}", report.freed_bytes(), cache_dir.display());
+    }
+    Ok(report)
+}
+
+/// List the top-level entries of every namespace
+///
+/// Caches are in use while GC runs (e.g. a browser profile), so paths that
+/// disappear during the scan are skipped rather than failing it.
+fn scan(cache_dir: &Path) -> Result<Vec<CacheEntry>> {
+    let mut entries = Vec::new();
+    let Some(top) = skip_not_found(fs::read_dir(cache_dir))
+        .with_context(|| format!("Failed to read cache directory: {}", cache_dir.display()))?
+    else {
+        return Ok(entries);
+    };
+
+    for item in top {
+        let Some(item) = skip_not_found(item)? else {
+            continue;
+        };
+        let path = item.path();
+        let Some(file_type) = skip_not_found(item.file_type())? else {
+            continue;
+        };
+        if file_type.is_dir() {
+            let namespace = item.file_name().to_string_lossy().into_owned();
+            let Some(children) = skip_not_found(fs::read_dir(&path))? else {
+                continue;
+            };
+            for child in children {
+                let Some(child) = skip_not_found(child)? else {
+                    continue;
+                };
+                let child = child.path();
+                if let Some((bytes, last_used)) = measure(&child)? {
+                    entries.push(CacheEntry { namespace: namespace.clone(), path: child, bytes, last_used });
+                }
+            }
+        } else if let Some((bytes, last_used)) = measure(&path)? {
+            entries.push(CacheEntry { namespace: String::new(), path, bytes, last_used });
+        }
+    }
+    Ok(entries)
+}
+
+/// `Ok(None)` for a path that no longer exists
+fn skip_not_found<T>(result: std::io::Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Summarize entries per namespace
+fn usage(entries: &[CacheEntry]) -> Vec<NamespaceUsage> {
+    let mut usage = std::collections::BTreeMap::<&str, NamespaceUsage>::new();
+    for entry in entries {
+        let u = usage.entry(&entry.namespace).or_insert_with(|| NamespaceUsage {
+            namespace: entry.namespace.clone(),
+            ..Default::default()
+        });
+        u.bytes += entry.bytes;
+        u.entries += 1;
+    }
+    usage.into_values().collect()
+}
+
+/// Total size and most recent use of a file or directory tree
+///
+/// Symlinks are measured as themselves and never followed. A directory's own
+/// timestamps only count when it is empty, since scanning it updates them.
+/// Returns `None` if `path` was removed; removed children are not counted.
+fn measure(path: &Path) -> Result<Option<(u64, SystemTime)>> {
+    let Some(metadata) = skip_not_found(fs::symlink_metadata(path))? else {
+        return Ok(None);
+    };
+    if !metadata.is_dir() {
+        return Ok(Some((metadata.len(), last_used(&metadata))));
+    }
+    let Some(children) = skip_not_found(fs::read_dir(path))? else {
+        return Ok(None);
+    };
+
+    let mut bytes = 0;
+    let mut newest = None;
+    for child in children {
+        let Some(child) = skip_not_found(child)? else {
+            continue;
+        };
+        if let Some((child_bytes, child_used)) = measure(&child.path())? {
+            bytes += child_bytes;
+            newest = newest.max(Some(child_used));
+        }
+    }
+    Ok(Some((bytes, newest.unwrap_or_else(|| metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)))))
+}
+
+fn last_used(metadata: &fs::Metadata) -> SystemTime {
+    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+    let accessed = metadata.accessed().unwrap_or(SystemTime::UNIX_EPOCH);
+    modified.max(accessed)
+}
+
+/// Remove a cache entry; one that is already gone counts as removed
+fn remove(path: &Path) -> std::io::Result<()> {
+    let result = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) => Err(e),
+    };
+    match result {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_aged(path: &Path, bytes: usize, age: Duration, now: SystemTime) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![0u8; bytes]).unwrap();
+        let time = now - age;
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_times(fs::FileTimes::new().set_accessed(time).set_modified(time)).unwrap();
+    }
+
+    #[test]
+    fn test_gc_evicts_expired_then_lru() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let now = SystemTime::now();
+        let day = Duration::from_secs(24 * 60 * 60);
+        write_aged(&root.join("downloads/old.zip"), 100, day * 60, now);
+        write_aged(&root.join("downloads/recent.zip"), 100, day, now);
+        write_aged(&root.join("builds/a/out.bin"), 300, day * 2, now);
+        write_aged(&root.join("chrome/profile"), 50, Duration::ZERO, now);
+
+        let policy = CachePolicy { max_bytes: Some(200), max_age: Some(day * 30), dry_run: true };
+        let report = gc(root, &policy, now).unwrap();
+        assert_eq!(report.total_bytes(), 550);
+        assert_eq!(report.usage.iter().map(|u| u.namespace.as_str()).collect::<Vec<_>>(), ["builds", "chrome", "downloads"]);
+        let evicted: Vec<_> = report.evicted.iter().map(|(e, r)| (e.path.strip_prefix(root).unwrap().to_path_buf(), *r)).collect();
+        assert_eq!(
+            evicted,
+            [
+                (PathBuf::from("downloads/old.zip"), EvictionReason::Expired),
+                (PathBuf::from("builds/a"), EvictionReason::OverQuota),
+            ]
+        );
+        assert!(root.join("downloads/old.zip").exists(), "dry-run must not delete");
+
+        let report = gc(root, &CachePolicy { dry_run: false, ..policy }, now).unwrap();
+        assert_eq!(report.freed_bytes(), 400);
+        assert!(!root.join("builds/a").exists());
+        assert!(root.join("downloads/recent.zip").exists());
+    }
+
+    #[test]
+    fn test_vanished_paths_are_skipped() {
+        let temp = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+        write_aged(&temp.path().join("chrome/profile/a"), 10, Duration::ZERO, now);
+
+        assert_eq!(measure(&temp.path().join("chrome/gone")).unwrap(), None);
+        assert!(remove(&temp.path().join("chrome/gone")).is_ok());
+        assert_eq!(measure(&temp.path().join("chrome")).unwrap().unwrap().0, 10);
+    }
+}
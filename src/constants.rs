@@ -5,6 +5,8 @@
 //! - Category names (used in tool metadata and routing)
 //! - Port assignments (used by HTTP MCP servers)
 //! - Category-to-port mappings
+//! - Lists of all categories and tool names (used for schema generation)
+//! - Tool registry linking each tool to its category ([`TOOLS`])
 //!
 //! All references to these values MUST use these constants - no hardcoded strings/numbers.
 
@@ -22,12 +24,73 @@ pub struct Category {
     pub icon: char,
 }
 
+impl Category {
+    /// All categories, in alphabetical order by name
+    pub fn all() -> &'static [&'static Category] {
+        CATEGORIES
+    }
+
+    /// Look up a category by its exact name
+    pub fn from_name(name: &str) -> Option<&'static Category> {
+        CATEGORIES.iter().copied().find(|category| category.name == name)
+    }
+
+    /// Category whose server handles this category's tools
+    ///
+    /// Memory tools are served by candle-agent; every other category is
+    /// served by its own server. `None` for categories not in [`CATEGORIES`].
+    pub fn backend(&self) -> Option<&'static Category> {
+        if self.name == CATEGORY_MEMORY.name {
+            Some(CATEGORY_CANDLE_AGENT)
+        } else {
+            Self::from_name(self.name)
+        }
+    }
+
+    /// Default HTTP port of the server handling this category
+    pub fn port(&self) -> Option<u16> {
+        let backend = self.backend()?;
+        CATEGORY_PORTS.iter().find(|(category, _)| category.name == backend.name).map(|(_, port)| *port)
+    }
+}
+
+/// Category whose server listens on a default port
+pub fn category_for_port(port: u16) -> Option<&'static Category> {
+    CATEGORY_PORTS.iter().find(|(_, p)| *p == port).map(|(category, _)| *category)
+}
+
 impl std::fmt::Display for Category {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.name)
     }
 }
 
+impl std::str::FromStr for Category {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> anyhow::Result<Self> {
+        Self::from_name(name).copied().ok_or_else(|| {
+            let known: Vec<&str> = CATEGORIES.iter().map(|c| c.name).collect();
+            anyhow::anyhow!("Unknown category '{}'. Known categories: {}", name, known.join(", "))
+        })
+    }
+}
+
+/// Serialized as the category name
+impl serde::Serialize for Category {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name)
+    }
+}
+
+/// Deserialized from a category name; unknown names are an error
+impl<'de> serde::Deserialize<'de> for Category {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 // ============================================================================
 // CATEGORY CONSTANTS
 // ============================================================================
@@ -132,172 +195,367 @@ pub const CATEGORY_TERMINAL: &Category = &Category {
 // TOOL NAME CONSTANTS
 // ============================================================================
 
-// Memory tools (served by candle-agent)
-pub const MEMORY_MEMORIZE: &str = "memory_memorize";
-pub const MEMORY_RECALL: &str = "memory_recall";
-pub const MEMORY_LIST_LIBRARIES: &str = "memory_list_libraries";
-pub const MEMORY_CHECK_MEMORIZE_STATUS: &str = "memory_check_memorize_status";
-
-// Claude agent tools
-pub const CLAUDE_AGENT: &str = "claude_agent";
-
-// Filesystem tools
-pub const FS_CREATE_DIRECTORY: &str = "fs_create_directory";
-pub const FS_DELETE_DIRECTORY: &str = "fs_delete_directory";
-pub const FS_DELETE_FILE: &str = "fs_delete_file";
-pub const FS_EDIT_BLOCK: &str = "fs_edit_block";
-pub const FS_GET_FILE_INFO: &str = "fs_get_file_info";
-pub const FS_LIST_DIRECTORY: &str = "fs_list_directory";
-pub const FS_MOVE_FILE: &str = "fs_move_file";
-pub const FS_READ_FILE: &str = "fs_read_file";
-pub const FS_READ_MULTIPLE_FILES: &str = "fs_read_multiple_files";
-pub const FS_SEARCH: &str = "fs_search";
-pub const FS_WRITE_FILE: &str = "fs_write_file";
-
-// Git tools
-pub const GIT_ADD: &str = "git_add";
-pub const GIT_BRANCH_CREATE: &str = "git_branch_create";
-pub const GIT_BRANCH_DELETE: &str = "git_branch_delete";
-pub const GIT_BRANCH_LIST: &str = "git_branch_list";
-pub const GIT_BRANCH_RENAME: &str = "git_branch_rename";
-pub const GIT_CHERRY_PICK: &str = "git_cherry_pick";
-pub const GIT_CHECKOUT: &str = "git_checkout";
-pub const GIT_CLONE: &str = "git_clone";
-pub const GIT_COMMIT: &str = "git_commit";
-pub const GIT_CONFIG_GET: &str = "git_config_get";
-pub const GIT_CONFIG_SET: &str = "git_config_set";
-pub const GIT_DIFF: &str = "git_diff";
-pub const GIT_DISCOVER: &str = "git_discover";
-pub const GIT_FETCH: &str = "git_fetch";
-pub const GIT_HISTORY: &str = "git_history";
-pub const GIT_INIT: &str = "git_init";
-pub const GIT_LOG: &str = "git_log";
-pub const GIT_MERGE: &str = "git_merge";
-pub const GIT_OPEN: &str = "git_open";
-pub const GIT_PULL: &str = "git_pull";
-pub const GIT_PUSH: &str = "git_push";
-pub const GIT_REBASE: &str = "git_rebase";
-pub const GIT_REMOTE_ADD: &str = "git_remote_add";
-pub const GIT_REMOTE_LIST: &str = "git_remote_list";
-pub const GIT_REMOTE_REMOVE: &str = "git_remote_remove";
-pub const GIT_RESET: &str = "git_reset";
-pub const GIT_REVERT: &str = "git_revert";
-pub const GIT_SHOW: &str = "git_show";
-pub const GIT_STASH: &str = "git_stash";
-pub const GIT_STASH_APPLY: &str = "git_stash_apply";
-pub const GIT_STASH_LIST: &str = "git_stash_list";
-pub const GIT_STASH_POP: &str = "git_stash_pop";
-pub const GIT_STATUS: &str = "git_status";
-pub const GIT_TAG: &str = "git_tag";
-pub const GIT_TAG_CREATE: &str = "git_tag_create";
-pub const GIT_TAG_LIST: &str = "git_tag_list";
-pub const GIT_WORKTREE_ADD: &str = "git_worktree_add";
-pub const GIT_WORKTREE_LIST: &str = "git_worktree_list";
-pub const GIT_WORKTREE_LOCK: &str = "git_worktree_lock";
-pub const GIT_WORKTREE_PRUNE: &str = "git_worktree_prune";
-pub const GIT_WORKTREE_REMOVE: &str = "git_worktree_remove";
-pub const GIT_WORKTREE_UNLOCK: &str = "git_worktree_unlock";
-
-// GitHub tools
-pub const GITHUB_ACCEPT_REPO_INVITATION: &str = "github_accept_repo_invitation";
-pub const GITHUB_ADD_ISSUE_COMMENT: &str = "github_add_issue_comment";
-pub const GITHUB_ADD_PULL_REQUEST_REVIEW_COMMENT: &str = "github_add_pull_request_review_comment";
-pub const GITHUB_CODE_SCANNING_ALERTS: &str = "github_code_scanning_alerts";
-pub const GITHUB_CREATE_BRANCH: &str = "github_create_branch";
-pub const GITHUB_CREATE_ISSUE: &str = "github_create_issue";
-pub const GITHUB_CREATE_OR_UPDATE_FILE: &str = "github_create_or_update_file";
-pub const GITHUB_CREATE_PULL_REQUEST: &str = "github_create_pull_request";
-pub const GITHUB_CREATE_PULL_REQUEST_REVIEW: &str = "github_create_pull_request_review";
-pub const GITHUB_CREATE_RELEASE: &str = "github_create_release";
-pub const GITHUB_CREATE_REPOSITORY: &str = "github_create_repository";
-pub const GITHUB_DELETE_BRANCH: &str = "github_delete_branch";
-pub const GITHUB_DELETE_FILE: &str = "github_delete_file";
-pub const GITHUB_FORK_REPOSITORY: &str = "github_fork_repository";
-pub const GITHUB_GET_COMMIT: &str = "github_get_commit";
-pub const GITHUB_GET_FILE_CONTENTS: &str = "github_get_file_contents";
-pub const GITHUB_GET_ISSUE: &str = "github_get_issue";
-pub const GITHUB_GET_ISSUE_COMMENTS: &str = "github_get_issue_comments";
-pub const GITHUB_GET_ME: &str = "github_get_me";
-pub const GITHUB_GET_PULL_REQUEST_FILES: &str = "github_get_pull_request_files";
-pub const GITHUB_GET_PULL_REQUEST_REVIEWS: &str = "github_get_pull_request_reviews";
-pub const GITHUB_GET_PULL_REQUEST_STATUS: &str = "github_get_pull_request_status";
-pub const GITHUB_LIST_BRANCHES: &str = "github_list_branches";
-pub const GITHUB_LIST_COMMITS: &str = "github_list_commits";
-pub const GITHUB_LIST_ISSUES: &str = "github_list_issues";
-pub const GITHUB_LIST_PULL_REQUESTS: &str = "github_list_pull_requests";
-pub const GITHUB_LIST_REPOS: &str = "github_list_repos";
-pub const GITHUB_MERGE_PULL_REQUEST: &str = "github_merge_pull_request";
-pub const GITHUB_PENDING_INVITATIONS: &str = "github_pending_invitations";
-pub const GITHUB_PUSH_FILE: &str = "github_push_file";
-pub const GITHUB_PUSH_FILES: &str = "github_push_files";
-pub const GITHUB_REQUEST_COPILOT_REVIEW: &str = "github_request_copilot_review";
-pub const GITHUB_SEARCH_CODE: &str = "github_search_code";
-pub const GITHUB_SEARCH_ISSUES: &str = "github_search_issues";
-pub const GITHUB_SEARCH_REPOSITORIES: &str = "github_search_repositories";
-pub const GITHUB_SEARCH_USERS: &str = "github_search_users";
-pub const GITHUB_SECRET_SCANNING_ALERTS: &str = "github_secret_scanning_alerts";
-pub const GITHUB_UPDATE_ISSUE: &str = "github_update_issue";
-pub const GITHUB_UPDATE_PULL_REQUEST: &str = "github_update_pull_request";
-
-// Browser tools
-pub const BROWSER_AGENT: &str = "browser_agent";
-pub const BROWSER_AGENT_KILL: &str = "browser_agent_kill";
-pub const BROWSER_CLICK: &str = "browser_click";
-pub const BROWSER_EVAL: &str = "browser_eval";
-pub const BROWSER_EXTRACT_TEXT: &str = "browser_extract_text";
-pub const BROWSER_NAVIGATE: &str = "browser_navigate";
-pub const BROWSER_RESEARCH: &str = "browser_research";
-pub const BROWSER_SCREENSHOT: &str = "browser_screenshot";
-pub const BROWSER_SCROLL: &str = "browser_scroll";
-pub const BROWSER_TYPE_TEXT: &str = "browser_type_text";
-pub const BROWSER_WEB_SEARCH: &str = "browser_web_search";
-
-// Database tools
-pub const DB_EXECUTE_SQL: &str = "db_execute_sql";
-pub const DB_LIST_SCHEMAS: &str = "db_list_schemas";
-pub const DB_LIST_TABLES: &str = "db_list_tables";
-pub const DB_POOL_STATS: &str = "db_pool_stats";
-pub const DB_STORED_PROCEDURES: &str = "db_stored_procedures";
-pub const DB_TABLE_INDEXES: &str = "db_table_indexes";
-pub const DB_TABLE_SCHEMA: &str = "db_table_schema";
-
-// Terminal tools
-pub const START_TERMINAL: &str = "start_terminal";
-pub const TERMINAL: &str = "terminal";
-
-// Process tools
-pub const PROCESS_KILL: &str = "process_kill";
-pub const PROCESS_LIST: &str = "process_list";
-
-// Introspection tools
-pub const INTROSPECTION_GET_EVENTS: &str = "introspection_get_events";
-pub const INTROSPECTION_INSPECT_TOOL_CALLS: &str = "introspection_inspect_tool_calls";
-pub const INTROSPECTION_INSPECT_USAGE_STATS: &str = "introspection_inspect_usage_stats";
-pub const INTROSPECTION_LIST_TOOLS: &str = "introspection_list_tools";
-pub const INSPECT_TOOL_CALLS: &str = "inspect_tool_calls";
-pub const INSPECT_USAGE_STATS: &str = "inspect_usage_stats";
-
-// Prompt tools
-pub const PROMPT_ADD: &str = "prompt_add";
-pub const PROMPT_DELETE: &str = "prompt_delete";
-pub const PROMPT_EDIT: &str = "prompt_edit";
-pub const PROMPT_GET: &str = "prompt_get";
-
-// Config tools
-pub const CONFIG_GET: &str = "config_get";
-pub const CONFIG_SET: &str = "config_set";
-
-// Citescrape tools
-pub const CITESCRAPE_FETCH: &str = "fetch";
-pub const CITESCRAPE_SCRAPE_URL: &str = "scrape_url";
-pub const CITESCRAPE_WEB_SEARCH: &str = "web_search";
-pub const FETCH: &str = "fetch";
-pub const SCRAPE_URL: &str = "scrape_url";
-pub const WEB_SEARCH: &str = "web_search";
-
-// Reasoning tools
-pub const REASONER: &str = "reasoner";
-pub const SEQUENTIAL_THINKING: &str = "sequential_thinking";
+/// Defines every tool name constant together with the lists built from them
+///
+/// Each canonical tool is declared once, with its metadata, and becomes a
+/// constant, an entry of [`TOOLS`] and an entry of [`TOOL_NAMES`]; a tool
+/// constant cannot exist without being registered. Aliases become deprecated
+/// constants listed in [`TOOL_ALIASES`] and [`TOOL_NAMES`]. Duplicates are
+/// deprecated constants sharing the value of a canonical tool.
+macro_rules! tools {
+    (
+        tools {
+            $($name:ident = $value:literal, $category:ident, $access:ident, $effect:ident, $description:literal;)*
+        }
+        aliases {
+            $($(#[$alias_meta:meta])* $alias:ident = $alias_value:literal => $target:ident;)*
+        }
+        duplicates {
+            $($(#[$duplicate_meta:meta])* $duplicate:ident = $original:ident;)*
+        }
+    ) => {
+        $(pub const $name: &str = $value;)*
+        $($(#[$alias_meta])* pub const $alias: &str = $alias_value;)*
+        $($(#[$duplicate_meta])* pub const $duplicate: &str = $original;)*
+
+        /// All tools with their category and metadata, one entry per canonical name
+        ///
+        /// Deprecated aliases are not listed; they resolve through [`TOOL_ALIASES`].
+        pub const TOOLS: &[ToolDef] = &[$(tool($name, $category, $access, $effect, $description)),*];
+
+        /// Deprecated tool names and the canonical name they stand for
+        ///
+        /// Older toolset files and usage stats use these names; the toolset loader
+        /// rewrites them (with a warning) so counts aren't split between two names.
+        #[allow(deprecated)]
+        pub const TOOL_ALIASES: &[(&str, &str)] = &[$(($alias, $target)),*];
+
+        /// All distinct tool names: every tool in [`TOOLS`], then every alias in [`TOOL_ALIASES`]
+        ///
+        /// Duplicate constants (e.g. `FETCH` for `CITESCRAPE_FETCH`) add no entry.
+        /// Used to generate the tool name enum in the toolset JSON Schema, so toolset
+        /// files using an alias still validate.
+        #[allow(deprecated)]
+        pub const TOOL_NAMES: &[&str] = &[$($name,)* $($alias,)*];
+    };
+}
+
+tools! {
+    tools {
+        // Memory tools (served by candle-agent)
+        MEMORY_MEMORIZE = "memory_memorize", CATEGORY_MEMORY, RW, SAFE, "Store content in a memory library";
+        MEMORY_RECALL = "memory_recall", CATEGORY_MEMORY, RO, SAFE, "Search a memory library for relevant content";
+        MEMORY_LIST_LIBRARIES = "memory_list_libraries", CATEGORY_MEMORY, RO, SAFE, "List memory libraries";
+        MEMORY_CHECK_MEMORIZE_STATUS = "memory_check_memorize_status", CATEGORY_MEMORY, RO, SAFE, "Check the progress of a memorize operation";
+        // Claude agent tools
+        CLAUDE_AGENT = "claude_agent", CATEGORY_CLAUDE_AGENT, RW, SAFE, "Delegate a task to a Claude sub-agent";
+        // Filesystem tools
+        FS_CREATE_DIRECTORY = "fs_create_directory", CATEGORY_FILESYSTEM, RW, SAFE, "Create a directory";
+        FS_DELETE_DIRECTORY = "fs_delete_directory", CATEGORY_FILESYSTEM, RW, DESTRUCTIVE, "Delete a directory and its contents";
+        FS_DELETE_FILE = "fs_delete_file", CATEGORY_FILESYSTEM, RW, DESTRUCTIVE, "Delete a file";
+        FS_EDIT_BLOCK = "fs_edit_block", CATEGORY_FILESYSTEM, RW, SAFE, "Replace a block of text in a file";
+        FS_GET_FILE_INFO = "fs_get_file_info", CATEGORY_FILESYSTEM, RO, SAFE, "Get file metadata";
+        FS_LIST_DIRECTORY = "fs_list_directory", CATEGORY_FILESYSTEM, RO, SAFE, "List directory contents";
+        FS_MOVE_FILE = "fs_move_file", CATEGORY_FILESYSTEM, RW, DESTRUCTIVE, "Move or rename a file";
+        FS_READ_FILE = "fs_read_file", CATEGORY_FILESYSTEM, RO, SAFE, "Read a file";
+        FS_READ_MULTIPLE_FILES = "fs_read_multiple_files", CATEGORY_FILESYSTEM, RO, SAFE, "Read several files at once";
+        FS_SEARCH = "fs_search", CATEGORY_FILESYSTEM, RO, SAFE, "Search file names and contents";
+        FS_WRITE_FILE = "fs_write_file", CATEGORY_FILESYSTEM, RW, DESTRUCTIVE, "Write or overwrite a file";
+        // Git tools
+        GIT_ADD = "git_add", CATEGORY_GIT, RW, SAFE, "Stage changes";
+        GIT_BRANCH_CREATE = "git_branch_create", CATEGORY_GIT, RW, SAFE, "Create a branch";
+        GIT_BRANCH_DELETE = "git_branch_delete", CATEGORY_GIT, RW, DESTRUCTIVE, "Delete a branch";
+        GIT_BRANCH_LIST = "git_branch_list", CATEGORY_GIT, RO, SAFE, "List branches";
+        GIT_BRANCH_RENAME = "git_branch_rename", CATEGORY_GIT, RW, SAFE, "Rename a branch";
+        GIT_CHERRY_PICK = "git_cherry_pick", CATEGORY_GIT, RW, SAFE, "Apply commits onto the current branch";
+        GIT_CHECKOUT = "git_checkout", CATEGORY_GIT, RW, DESTRUCTIVE, "Switch branches or restore files";
+        GIT_CLONE = "git_clone", CATEGORY_GIT, RW, SAFE, "Clone a repository";
+        GIT_COMMIT = "git_commit", CATEGORY_GIT, RW, SAFE, "Record staged changes";
+        GIT_CONFIG_GET = "git_config_get", CATEGORY_GIT, RO, SAFE, "Read a git config value";
+        GIT_CONFIG_SET = "git_config_set", CATEGORY_GIT, RW, SAFE, "Set a git config value";
+        GIT_DIFF = "git_diff", CATEGORY_GIT, RO, SAFE, "Show changes between commits or the working tree";
+        GIT_DISCOVER = "git_discover", CATEGORY_GIT, RO, SAFE, "Find the repository containing a path";
+        GIT_FETCH = "git_fetch", CATEGORY_GIT, RW, SAFE, "Download objects and refs from a remote";
+        GIT_HISTORY = "git_history", CATEGORY_GIT, RO, SAFE, "Show the history of a file or path";
+        GIT_INIT = "git_init", CATEGORY_GIT, RW, SAFE, "Create a repository";
+        GIT_LOG = "git_log", CATEGORY_GIT, RO, SAFE, "Show commit logs";
+        GIT_MERGE = "git_merge", CATEGORY_GIT, RW, SAFE, "Merge a branch into the current branch";
+        GIT_OPEN = "git_open", CATEGORY_GIT, RO, SAFE, "Open a repository";
+        GIT_PULL = "git_pull", CATEGORY_GIT, RW, SAFE, "Fetch and integrate remote changes";
+        GIT_PUSH = "git_push", CATEGORY_GIT, RW, DESTRUCTIVE, "Update remote refs";
+        GIT_REBASE = "git_rebase", CATEGORY_GIT, RW, DESTRUCTIVE, "Reapply commits on another base";
+        GIT_REMOTE_ADD = "git_remote_add", CATEGORY_GIT, RW, SAFE, "Add a remote";
+        GIT_REMOTE_LIST = "git_remote_list", CATEGORY_GIT, RO, SAFE, "List remotes";
+        GIT_REMOTE_REMOVE = "git_remote_remove", CATEGORY_GIT, RW, SAFE, "Remove a remote";
+        GIT_RESET = "git_reset", CATEGORY_GIT, RW, DESTRUCTIVE, "Reset the current branch to a commit";
+        GIT_REVERT = "git_revert", CATEGORY_GIT, RW, SAFE, "Create commits that undo earlier commits";
+        GIT_SHOW = "git_show", CATEGORY_GIT, RO, SAFE, "Show a commit or object";
+        GIT_STASH = "git_stash", CATEGORY_GIT, RW, SAFE, "Stash working tree changes";
+        GIT_STASH_APPLY = "git_stash_apply", CATEGORY_GIT, RW, SAFE, "Apply a stash";
+        GIT_STASH_LIST = "git_stash_list", CATEGORY_GIT, RO, SAFE, "List stashes";
+        GIT_STASH_POP = "git_stash_pop", CATEGORY_GIT, RW, SAFE, "Apply and drop a stash";
+        GIT_STATUS = "git_status", CATEGORY_GIT, RO, SAFE, "Show working tree status";
+        GIT_TAG_CREATE = "git_tag_create", CATEGORY_GIT, RW, SAFE, "Create a tag";
+        GIT_TAG_LIST = "git_tag_list", CATEGORY_GIT, RO, SAFE, "List tags";
+        GIT_WORKTREE_ADD = "git_worktree_add", CATEGORY_GIT, RW, SAFE, "Add a worktree";
+        GIT_WORKTREE_LIST = "git_worktree_list", CATEGORY_GIT, RO, SAFE, "List worktrees";
+        GIT_WORKTREE_LOCK = "git_worktree_lock", CATEGORY_GIT, RW, SAFE, "Lock a worktree";
+        GIT_WORKTREE_PRUNE = "git_worktree_prune", CATEGORY_GIT, RW, DESTRUCTIVE, "Prune stale worktree information";
+        GIT_WORKTREE_REMOVE = "git_worktree_remove", CATEGORY_GIT, RW, DESTRUCTIVE, "Remove a worktree";
+        GIT_WORKTREE_UNLOCK = "git_worktree_unlock", CATEGORY_GIT, RW, SAFE, "Unlock a worktree";
+        // GitHub tools
+        GITHUB_ACCEPT_REPO_INVITATION = "github_accept_repo_invitation", CATEGORY_GITHUB, RW, SAFE, "Accept a repository invitation";
+        GITHUB_ADD_ISSUE_COMMENT = "github_add_issue_comment", CATEGORY_GITHUB, RW, SAFE, "Comment on an issue";
+        GITHUB_ADD_PULL_REQUEST_REVIEW_COMMENT = "github_add_pull_request_review_comment", CATEGORY_GITHUB, RW, SAFE, "Comment on a pull request review";
+        GITHUB_CODE_SCANNING_ALERTS = "github_code_scanning_alerts", CATEGORY_GITHUB, RO, SAFE, "List code scanning alerts";
+        GITHUB_CREATE_BRANCH = "github_create_branch", CATEGORY_GITHUB, RW, SAFE, "Create a branch";
+        GITHUB_CREATE_ISSUE = "github_create_issue", CATEGORY_GITHUB, RW, SAFE, "Create an issue";
+        GITHUB_CREATE_OR_UPDATE_FILE = "github_create_or_update_file", CATEGORY_GITHUB, RW, DESTRUCTIVE, "Create or overwrite a file in a repository";
+        GITHUB_CREATE_PULL_REQUEST = "github_create_pull_request", CATEGORY_GITHUB, RW, SAFE, "Open a pull request";
+        GITHUB_CREATE_PULL_REQUEST_REVIEW = "github_create_pull_request_review", CATEGORY_GITHUB, RW, SAFE, "Review a pull request";
+        GITHUB_CREATE_RELEASE = "github_create_release", CATEGORY_GITHUB, RW, SAFE, "Create a release";
+        GITHUB_CREATE_REPOSITORY = "github_create_repository", CATEGORY_GITHUB, RW, SAFE, "Create a repository";
+        GITHUB_DELETE_BRANCH = "github_delete_branch", CATEGORY_GITHUB, RW, DESTRUCTIVE, "Delete a branch";
+        GITHUB_DELETE_FILE = "github_delete_file", CATEGORY_GITHUB, RW, DESTRUCTIVE, "Delete a file from a repository";
+        GITHUB_FORK_REPOSITORY = "github_fork_repository", CATEGORY_GITHUB, RW, SAFE, "Fork a repository";
+        GITHUB_GET_COMMIT = "github_get_commit", CATEGORY_GITHUB, RO, SAFE, "Get a commit";
+        GITHUB_GET_FILE_CONTENTS = "github_get_file_contents", CATEGORY_GITHUB, RO, SAFE, "Get file contents";
+        GITHUB_GET_ISSUE = "github_get_issue", CATEGORY_GITHUB, RO, SAFE, "Get an issue";
+        GITHUB_GET_ISSUE_COMMENTS = "github_get_issue_comments", CATEGORY_GITHUB, RO, SAFE, "Get issue comments";
+        GITHUB_GET_ME = "github_get_me", CATEGORY_GITHUB, RO, SAFE, "Get the authenticated user";
+        GITHUB_GET_PULL_REQUEST_FILES = "github_get_pull_request_files", CATEGORY_GITHUB, RO, SAFE, "Get files changed in a pull request";
+        GITHUB_GET_PULL_REQUEST_REVIEWS = "github_get_pull_request_reviews", CATEGORY_GITHUB, RO, SAFE, "Get pull request reviews";
+        GITHUB_GET_PULL_REQUEST_STATUS = "github_get_pull_request_status", CATEGORY_GITHUB, RO, SAFE, "Get pull request check status";
+        GITHUB_LIST_BRANCHES = "github_list_branches", CATEGORY_GITHUB, RO, SAFE, "List branches";
+        GITHUB_LIST_COMMITS = "github_list_commits", CATEGORY_GITHUB, RO, SAFE, "List commits";
+        GITHUB_LIST_ISSUES = "github_list_issues", CATEGORY_GITHUB, RO, SAFE, "List issues";
+        GITHUB_LIST_PULL_REQUESTS = "github_list_pull_requests", CATEGORY_GITHUB, RO, SAFE, "List pull requests";
+        GITHUB_LIST_REPOS = "github_list_repos", CATEGORY_GITHUB, RO, SAFE, "List repositories";
+        GITHUB_MERGE_PULL_REQUEST = "github_merge_pull_request", CATEGORY_GITHUB, RW, SAFE, "Merge a pull request";
+        GITHUB_PENDING_INVITATIONS = "github_pending_invitations", CATEGORY_GITHUB, RO, SAFE, "List pending repository invitations";
+        GITHUB_PUSH_FILE = "github_push_file", CATEGORY_GITHUB, RW, DESTRUCTIVE, "Commit a file to a branch";
+        GITHUB_PUSH_FILES = "github_push_files", CATEGORY_GITHUB, RW, DESTRUCTIVE, "Commit several files to a branch";
+        GITHUB_REQUEST_COPILOT_REVIEW = "github_request_copilot_review", CATEGORY_GITHUB, RW, SAFE, "Request a Copilot review";
+        GITHUB_SEARCH_CODE = "github_search_code", CATEGORY_GITHUB, RO, SAFE, "Search code";
+        GITHUB_SEARCH_ISSUES = "github_search_issues", CATEGORY_GITHUB, RO, SAFE, "Search issues and pull requests";
+        GITHUB_SEARCH_REPOSITORIES = "github_search_repositories", CATEGORY_GITHUB, RO, SAFE, "Search repositories";
+        GITHUB_SEARCH_USERS = "github_search_users", CATEGORY_GITHUB, RO, SAFE, "Search users";
+        GITHUB_SECRET_SCANNING_ALERTS = "github_secret_scanning_alerts", CATEGORY_GITHUB, RO, SAFE, "List secret scanning alerts";
+        GITHUB_UPDATE_ISSUE = "github_update_issue", CATEGORY_GITHUB, RW, SAFE, "Update an issue";
+        GITHUB_UPDATE_PULL_REQUEST = "github_update_pull_request", CATEGORY_GITHUB, RW, SAFE, "Update a pull request";
+        // Browser tools
+        BROWSER_AGENT = "browser_agent", CATEGORY_BROWSER, RW, SAFE, "Run an autonomous browsing task";
+        BROWSER_AGENT_KILL = "browser_agent_kill", CATEGORY_BROWSER, RW, SAFE, "Stop a browsing task";
+        BROWSER_CLICK = "browser_click", CATEGORY_BROWSER, RW, SAFE, "Click an element";
+        BROWSER_EVAL = "browser_eval", CATEGORY_BROWSER, RW, SAFE, "Evaluate JavaScript in the page";
+        BROWSER_EXTRACT_TEXT = "browser_extract_text", CATEGORY_BROWSER, RO, SAFE, "Extract text from the page";
+        BROWSER_NAVIGATE = "browser_navigate", CATEGORY_BROWSER, RW, SAFE, "Navigate to a URL";
+        BROWSER_RESEARCH = "browser_research", CATEGORY_BROWSER, RO, SAFE, "Research a topic across several pages";
+        BROWSER_SCREENSHOT = "browser_screenshot", CATEGORY_BROWSER, RO, SAFE, "Take a screenshot";
+        BROWSER_SCROLL = "browser_scroll", CATEGORY_BROWSER, RW, SAFE, "Scroll the page";
+        BROWSER_TYPE_TEXT = "browser_type_text", CATEGORY_BROWSER, RW, SAFE, "Type text into an element";
+        BROWSER_WEB_SEARCH = "browser_web_search", CATEGORY_BROWSER, RO, SAFE, "Search the web";
+        // Database tools
+        DB_EXECUTE_SQL = "db_execute_sql", CATEGORY_DATABASE, RW, DESTRUCTIVE, "Execute SQL";
+        DB_LIST_SCHEMAS = "db_list_schemas", CATEGORY_DATABASE, RO, SAFE, "List schemas";
+        DB_LIST_TABLES = "db_list_tables", CATEGORY_DATABASE, RO, SAFE, "List tables";
+        DB_POOL_STATS = "db_pool_stats", CATEGORY_DATABASE, RO, SAFE, "Show connection pool statistics";
+        DB_STORED_PROCEDURES = "db_stored_procedures", CATEGORY_DATABASE, RO, SAFE, "List stored procedures";
+        DB_TABLE_INDEXES = "db_table_indexes", CATEGORY_DATABASE, RO, SAFE, "List indexes of a table";
+        DB_TABLE_SCHEMA = "db_table_schema", CATEGORY_DATABASE, RO, SAFE, "Describe a table";
+        // Terminal tools
+        START_TERMINAL = "start_terminal", CATEGORY_TERMINAL, RW, DESTRUCTIVE, "Start a terminal session";
+        TERMINAL = "terminal", CATEGORY_TERMINAL, RW, DESTRUCTIVE, "Run commands in a terminal session";
+        // Process tools
+        PROCESS_KILL = "process_kill", CATEGORY_PROCESS, RW, DESTRUCTIVE, "Terminate a process";
+        PROCESS_LIST = "process_list", CATEGORY_PROCESS, RO, SAFE, "List processes";
+        // Introspection tools
+        INTROSPECTION_GET_EVENTS = "introspection_get_events", CATEGORY_INTROSPECTION, RO, SAFE, "Get recent server events";
+        INTROSPECTION_INSPECT_TOOL_CALLS = "introspection_inspect_tool_calls", CATEGORY_INTROSPECTION, RO, SAFE, "Inspect recent tool calls";
+        INTROSPECTION_INSPECT_USAGE_STATS = "introspection_inspect_usage_stats", CATEGORY_INTROSPECTION, RO, SAFE, "Inspect tool usage statistics";
+        INTROSPECTION_LIST_TOOLS = "introspection_list_tools", CATEGORY_INTROSPECTION, RO, SAFE, "List available tools";
+        // Prompt tools
+        PROMPT_ADD = "prompt_add", CATEGORY_PROMPT, RW, SAFE, "Add a prompt template";
+        PROMPT_DELETE = "prompt_delete", CATEGORY_PROMPT, RW, DESTRUCTIVE, "Delete a prompt template";
+        PROMPT_EDIT = "prompt_edit", CATEGORY_PROMPT, RW, SAFE, "Edit a prompt template";
+        PROMPT_GET = "prompt_get", CATEGORY_PROMPT, RO, SAFE, "Get or render a prompt template";
+        // Config tools
+        CONFIG_GET = "config_get", CATEGORY_CONFIG, RO, SAFE, "Read configuration values";
+        CONFIG_SET = "config_set", CATEGORY_CONFIG, RW, SAFE, "Set a configuration value";
+        // Citescrape tools
+        CITESCRAPE_FETCH = "fetch", CATEGORY_CITESCRAPE, RO, SAFE, "Fetch a URL as text";
+        CITESCRAPE_SCRAPE_URL = "scrape_url", CATEGORY_CITESCRAPE, RO, SAFE, "Crawl and index a site";
+        CITESCRAPE_WEB_SEARCH = "web_search", CATEGORY_CITESCRAPE, RO, SAFE, "Search the web";
+        // Reasoning tools
+        REASONER = "reasoner", CATEGORY_REASONER, RO, SAFE, "Reason through a problem";
+        SEQUENTIAL_THINKING = "sequential_thinking", CATEGORY_SEQUENTIAL_THINKING, RO, SAFE, "Think through a problem step by step";
+    }
+    aliases {
+        #[deprecated(note = "alias of GIT_TAG_CREATE; see TOOL_ALIASES")]
+        GIT_TAG = "git_tag" => GIT_TAG_CREATE;
+        #[deprecated(note = "alias of INTROSPECTION_INSPECT_TOOL_CALLS; see TOOL_ALIASES")]
+        INSPECT_TOOL_CALLS = "inspect_tool_calls" => INTROSPECTION_INSPECT_TOOL_CALLS;
+        #[deprecated(note = "alias of INTROSPECTION_INSPECT_USAGE_STATS; see TOOL_ALIASES")]
+        INSPECT_USAGE_STATS = "inspect_usage_stats" => INTROSPECTION_INSPECT_USAGE_STATS;
+    }
+    duplicates {
+        #[deprecated(note = "duplicate of CITESCRAPE_FETCH")]
+        FETCH = CITESCRAPE_FETCH;
+        #[deprecated(note = "duplicate of CITESCRAPE_SCRAPE_URL")]
+        SCRAPE_URL = CITESCRAPE_SCRAPE_URL;
+        #[deprecated(note = "duplicate of CITESCRAPE_WEB_SEARCH")]
+        WEB_SEARCH = CITESCRAPE_WEB_SEARCH;
+    }
+}
+
+// ============================================================================
+// REGISTRY LISTS
+// ============================================================================
+
+/// All categories, in alphabetical order by name
+pub const CATEGORIES: &[&Category] = &[
+    CATEGORY_BROWSER,
+    CATEGORY_CANDLE_AGENT,
+    CATEGORY_CITESCRAPE,
+    CATEGORY_CLAUDE_AGENT,
+    CATEGORY_CONFIG,
+    CATEGORY_DATABASE,
+    CATEGORY_FILESYSTEM,
+    CATEGORY_GIT,
+    CATEGORY_GITHUB,
+    CATEGORY_INTROSPECTION,
+    CATEGORY_MEMORY,
+    CATEGORY_PROCESS,
+    CATEGORY_PROMPT,
+    CATEGORY_REASONER,
+    CATEGORY_SEQUENTIAL_THINKING,
+    CATEGORY_TERMINAL,
+];
+
+// ============================================================================
+// TOOL REGISTRY
+// ============================================================================
+
+/// Metadata for a tool name
+///
+/// Links each tool to the category (and so the backend) that serves it, so
+/// routing tables don't have to maintain that mapping by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ToolDef {
+    /// Tool name as it appears in tool metadata and toolset files
+    pub name: &'static str,
+    /// Category of the backend serving the tool
+    pub category: &'static Category,
+    /// Only reads state; never modifies files, repositories or remote resources
+    pub read_only: bool,
+    /// May irreversibly delete or overwrite data
+    pub destructive: bool,
+    /// One-line description
+    pub description: &'static str,
+}
+
+const fn tool(
+    name: &'static str,
+    category: &'static Category,
+    read_only: bool,
+    destructive: bool,
+    description: &'static str,
+) -> ToolDef {
+    ToolDef { name, category, read_only, destructive, description }
+}
+
+// Shorthands for the `tools!` table above
+const RO: bool = true;
+const RW: bool = false;
+const SAFE: bool = false;
+const DESTRUCTIVE: bool = true;
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn is_registered(name: &str) -> bool {
+    let mut i = 0;
+    while i < TOOLS.len() {
+        if str_eq(TOOLS[i].name, name) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+const fn alias_target(name: &str) -> Option<&'static str> {
+    let mut i = 0;
+    while i < TOOL_ALIASES.len() {
+        if str_eq(TOOL_ALIASES[i].0, name) {
+            return Some(TOOL_ALIASES[i].1);
+        }
+        i += 1;
+    }
+    None
+}
+
+// Compile-time checks (registration itself is enforced by `tools!`): tool
+// names and aliases are all distinct, aliases point at registered tools, and
+// read-only tools are never destructive.
+const _: () = {
+    let mut i = 0;
+    while i < TOOL_NAMES.len() {
+        let mut j = i + 1;
+        while j < TOOL_NAMES.len() {
+            assert!(!str_eq(TOOL_NAMES[i], TOOL_NAMES[j]), "duplicate tool name");
+            j += 1;
+        }
+        i += 1;
+    }
+
+    let mut i = 0;
+    while i < TOOLS.len() {
+        assert!(!(TOOLS[i].read_only && TOOLS[i].destructive), "read-only tool marked destructive");
+        i += 1;
+    }
+
+    let mut i = 0;
+    while i < TOOL_ALIASES.len() {
+        assert!(is_registered(TOOL_ALIASES[i].1), "alias of an unregistered tool");
+        i += 1;
+    }
+};
+
+/// Canonical name of a tool, resolving deprecated aliases
+///
+/// Names that are not aliases (including unknown ones) are returned unchanged.
+pub fn canonical_tool_name(name: &str) -> &str {
+    alias_target(name).unwrap_or(name)
+}
+
+/// Look up a tool by name (deprecated aliases resolve to the canonical tool)
+pub fn tool_by_name(name: &str) -> Option<&'static ToolDef> {
+    let name = canonical_tool_name(name);
+    TOOLS.iter().find(|tool| tool.name == name)
+}
+
+/// All tools served by a category, in registry order
+pub fn tools_in_category(category: &Category) -> impl Iterator<Item = &'static ToolDef> + '_ {
+    TOOLS.iter().filter(move |tool| tool.category.name == category.name)
+}
+
+/// Category serving a tool
+pub fn category_of(tool: &str) -> Option<&'static Category> {
+    tool_by_name(tool).map(|tool| tool.category)
+}
 
 // ============================================================================
 // PORT ASSIGNMENTS
@@ -348,10 +606,10 @@ pub const PORT_TERMINAL: u16 = 30451;
 /// HTTP port for candle_agent MCP server (also serves memory tools)
 pub const PORT_CANDLE_AGENT: u16 = 30452;
 
-/// Minimum port in allocated range
+/// Minimum port in the default range
 pub const PORT_MIN: u16 = 30438;
 
-/// Maximum port in allocated range
+/// Maximum port in the default range
 pub const PORT_MAX: u16 = 30452;
 
 // ============================================================================
@@ -360,7 +618,8 @@ pub const PORT_MAX: u16 = 30452;
 
 /// Static mapping of categories to their assigned HTTP ports
 ///
-/// This is the canonical source of truth for port assignments.
+/// This is the canonical source of the default port assignments; runtime
+/// overrides are applied by [`PortMap`](crate::PortMap).
 /// Used by:
 /// - kodegen routing table (stdio/metadata/routing.rs)
 /// - kodegend daemon configuration (kodegend/src/config.rs)
@@ -400,6 +659,8 @@ mod tests {
         );
     }
 
+    /// Only the static defaults live in `PORT_MIN..=PORT_MAX`; overridden and
+    /// dynamically published ports may be anywhere (see `resolve_service_endpoint`)
     #[test]
     fn test_port_range() {
         for (cat, port) in CATEGORY_PORTS {
@@ -414,12 +675,83 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tool_names_unique() {
+        let mut names = TOOL_NAMES.to_vec();
+        let original_len = names.len();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), original_len, "Duplicate tool names found");
+    }
+
+    #[test]
+    fn test_every_tool_name_resolves() {
+        for name in TOOL_NAMES {
+            assert!(tool_by_name(name).is_some(), "{} is not in TOOLS", name);
+        }
+        assert_eq!(TOOL_NAMES.len(), TOOLS.len() + TOOL_ALIASES.len());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_aliases() {
+        assert_eq!(canonical_tool_name(GIT_TAG), GIT_TAG_CREATE);
+        assert_eq!(canonical_tool_name(INSPECT_TOOL_CALLS), INTROSPECTION_INSPECT_TOOL_CALLS);
+        assert_eq!(canonical_tool_name(GIT_TAG_CREATE), GIT_TAG_CREATE);
+        assert_eq!(canonical_tool_name("no_such_tool"), "no_such_tool");
+        assert_eq!(FETCH, CITESCRAPE_FETCH);
+
+        assert_eq!(tool_by_name(INSPECT_USAGE_STATS).unwrap().name, INTROSPECTION_INSPECT_USAGE_STATS);
+        assert!(TOOLS.iter().all(|tool| canonical_tool_name(tool.name) == tool.name));
+    }
+
+    #[test]
+    fn test_tool_lookups() {
+        assert_eq!(category_of(GIT_COMMIT), Some(CATEGORY_GIT));
+        assert_eq!(category_of(CITESCRAPE_FETCH), Some(CATEGORY_CITESCRAPE));
+        assert_eq!(category_of(MEMORY_RECALL), Some(CATEGORY_MEMORY));
+        assert_eq!(category_of("no_such_tool"), None);
+
+        let fs_delete = tool_by_name(FS_DELETE_FILE).unwrap();
+        assert!(fs_delete.destructive && !fs_delete.read_only);
+
+        let prompt: Vec<_> = tools_in_category(CATEGORY_PROMPT).map(|t| t.name).collect();
+        assert_eq!(prompt, [PROMPT_ADD, PROMPT_DELETE, PROMPT_EDIT, PROMPT_GET]);
+        for category in CATEGORIES {
+            for tool in tools_in_category(category) {
+                assert_eq!(tool.category, *category);
+            }
+        }
+    }
+
+    #[test]
+    fn test_category_lookups_and_serde() {
+        assert_eq!(Category::all().len(), 16);
+        assert_eq!(Category::from_name("git"), Some(CATEGORY_GIT));
+        assert_eq!(Category::from_name("Git"), None);
+        assert!("gti".parse::<Category>().unwrap_err().to_string().contains("Known categories"));
+
+        assert_eq!(CATEGORY_GIT.port(), Some(PORT_GIT));
+        assert_eq!(CATEGORY_MEMORY.port(), Some(PORT_CANDLE_AGENT));
+        assert_eq!(category_for_port(PORT_CANDLE_AGENT), Some(CATEGORY_CANDLE_AGENT));
+        assert_eq!(category_for_port(1), None);
+        for category in Category::all() {
+            assert!(category.port().is_some(), "{} has no port", category);
+        }
+
+        let json = serde_json::to_string(&[*CATEGORY_GIT, *CATEGORY_MEMORY]).unwrap();
+        assert_eq!(json, r#"["git","memory"]"#);
+        let parsed: Vec<Category> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, [*CATEGORY_GIT, *CATEGORY_MEMORY]);
+        assert!(serde_json::from_str::<Category>(r#""gti""#).is_err());
+    }
+
     #[test]
     fn test_category_count() {
         assert_eq!(
             CATEGORY_PORTS.len(),
-            14,
-            "Expected 14 category-port mappings (memory uses candle_agent port)"
+            15,
+            "Expected 15 category-port mappings (memory uses candle_agent port)"
         );
     }
 }
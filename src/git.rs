@@ -100,3 +100,62 @@ fn discover_git_root(current_dir: &Path) -> Result<PathBuf> {
 pub fn clear_git_root_cache() {
     GIT_ROOT_CACHE.write().clear();
 }
+
+/// Resolve the `info/exclude` file for a repository
+///
+/// Handles both regular checkouts (`.git/` directory) and linked worktrees,
+/// where `.git` is a file containing `gitdir: <path>` and the shared
+/// `info/exclude` lives in the common directory named by `<gitdir>/commondir`.
+///
+/// # Errors
+///
+/// Returns an error if `.git` is missing or a worktree `.git` file is malformed.
+pub fn info_exclude_path(git_root: &Path) -> Result<PathBuf> {
+    let dot_git = git_root.join(".git");
+    let git_dir = if dot_git.is_dir() {
+        dot_git
+    } else {
+        let content = std::fs::read_to_string(&dot_git)
+            .with_context(|| format!("Failed to read {}", dot_git.display()))?;
+        let target = content
+            .lines()
+            .find_map(|line| line.strip_prefix("gitdir:"))
+            .map(str::trim)
+            .with_context(|| format!("Malformed .git file (no 'gitdir:' line): {}", dot_git.display()))?;
+        git_root.join(target)
+    };
+
+    // Linked worktrees share info/exclude with the main repository
+    let common_dir = match std::fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common) => git_dir.join(common.trim()),
+        Err(_) => git_dir,
+    };
+
+    Ok(common_dir.join("info").join("exclude"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_info_exclude_path_for_checkout_and_worktree() {
+        let temp = tempfile::tempdir().unwrap();
+        let main = temp.path().join("main");
+        std::fs::create_dir_all(main.join(".git/worktrees/feature")).unwrap();
+        assert_eq!(info_exclude_path(&main).unwrap(), main.join(".git/info/exclude"));
+
+        let worktree = temp.path().join("feature");
+        std::fs::create_dir_all(&worktree).unwrap();
+        std::fs::write(worktree.join(".git"), format!("gitdir: {}\n", main.join(".git/worktrees/feature").display()))
+            .unwrap();
+        std::fs::write(main.join(".git/worktrees/feature/commondir"), "../..\n").unwrap();
+
+        let exclude = info_exclude_path(&worktree).unwrap();
+        assert!(exclude.ends_with("info/exclude"));
+        assert_eq!(
+            exclude.parent().unwrap().parent().unwrap().canonicalize().unwrap(),
+            main.join(".git").canonicalize().unwrap()
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! Typed infrastructure header context
+//!
+//! The stdio server passes its connection ID, working directory and git root
+//! to HTTP backends in the `X_KODEGEN_*` headers. [`InfraContext`] builds and
+//! parses those headers so every backend handles encoding and validation the
+//! same way.
+//!
+//! **Path encoding**: paths are percent-encoded from their raw OS bytes (the
+//! WTF-8 form on Windows), so non-UTF-8 and otherwise unrepresentable paths
+//! survive the round trip. Unreserved characters (`A-Z a-z 0-9 - . _ ~ /`)
+//! are sent as is; everything else becomes `%XX`. Decoding also accepts
+//! unencoded values from older clients, as long as they contain no `%`.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+
+use crate::{X_KODEGEN_CONNECTION_ID, X_KODEGEN_GITROOT, X_KODEGEN_PWD};
+
+/// Infrastructure context passed from the stdio server to backends
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfraContext {
+    /// Connection ID of the stdio connection (visible ASCII, no spaces)
+    pub connection_id: String,
+    /// Absolute working directory kodegen was spawned from
+    pub pwd: PathBuf,
+    /// Absolute git repository root, an ancestor of (or equal to) `pwd`
+    pub git_root: Option<PathBuf>,
+}
+
+impl InfraContext {
+    /// Create and validate a context
+    ///
+    /// # Errors
+    ///
+    /// See [`validate()`](Self::validate).
+    pub fn new(connection_id: impl Into<String>, pwd: PathBuf, git_root: Option<PathBuf>) -> Result<Self> {
+        let context = Self { connection_id: connection_id.into(), pwd, git_root };
+        context.validate()?;
+        Ok(context)
+    }
+
+    /// Context for the current process: current directory and its git root
+    pub fn current(connection_id: impl Into<String>) -> Result<Self> {
+        let pwd = std::env::current_dir().context("Failed to determine current directory")?;
+        let git_root = crate::git::find_git_root().ok();
+        Self::new(connection_id, pwd, git_root)
+    }
+
+    /// Check the invariants backends rely on
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection ID is empty or not visible ASCII,
+    /// a path is relative or contains `..`, or `git_root` is not an ancestor
+    /// of `pwd`.
+    pub fn validate(&self) -> Result<()> {
+        if self.connection_id.is_empty() || !self.connection_id.bytes().all(|b| b.is_ascii_graphic()) {
+            bail!("Invalid {}: must be non-empty visible ASCII", X_KODEGEN_CONNECTION_ID);
+        }
+        check_path(X_KODEGEN_PWD, &self.pwd)?;
+        if let Some(git_root) = &self.git_root {
+            check_path(X_KODEGEN_GITROOT, git_root)?;
+            if !self.pwd.starts_with(git_root) {
+                bail!(
+                    "Invalid {}: {} is not an ancestor of the working directory {}",
+                    X_KODEGEN_GITROOT,
+                    git_root.display(),
+                    self.pwd.display()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Header name/value pairs, ready to add to a request
+    pub fn to_headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![
+            (X_KODEGEN_CONNECTION_ID, self.connection_id.clone()),
+            (X_KODEGEN_PWD, encode_path(&self.pwd)),
+        ];
+        if let Some(git_root) = &self.git_root {
+            headers.push((X_KODEGEN_GITROOT, encode_path(git_root)));
+        }
+        headers
+    }
+
+    /// Parse and validate a context from request headers
+    ///
+    /// Header names are matched case-insensitively; unrelated headers are
+    /// ignored. The git root header is optional.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a required header is missing, a value cannot be
+    /// decoded, or the context fails [`validate()`](Self::validate).
+    pub fn from_headers<'a, I>(headers: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let mut connection_id = None;
+        let mut pwd = None;
+        let mut git_root = None;
+
+        for (name, value) in headers {
+            if name.eq_ignore_ascii_case(X_KODEGEN_CONNECTION_ID) {
+                connection_id = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case(X_KODEGEN_PWD) {
+                pwd = Some(decode_path(value.trim()).with_context(|| format!("Invalid {}", X_KODEGEN_PWD))?);
+            } else if name.eq_ignore_ascii_case(X_KODEGEN_GITROOT) {
+                git_root =
+                    Some(decode_path(value.trim()).with_context(|| format!("Invalid {}", X_KODEGEN_GITROOT))?);
+            }
+        }
+
+        Self::new(
+            connection_id.ok_or_else(|| anyhow!("Missing {} header", X_KODEGEN_CONNECTION_ID))?,
+            pwd.ok_or_else(|| anyhow!("Missing {} header", X_KODEGEN_PWD))?,
+            git_root,
+        )
+    }
+}
+
+fn check_path(header: &str, path: &Path) -> Result<()> {
+    if !path.is_absolute() {
+        bail!("Invalid {}: {} is not an absolute path", header, path.display());
+    }
+    if path.components().any(|c| c == Component::ParentDir) {
+        bail!("Invalid {}: {} contains '..'", header, path.display());
+    }
+    Ok(())
+}
+
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~' | b'/')
+}
+
+/// Percent-encode a path's raw OS bytes
+fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_encoded_bytes() {
+        if is_unreserved(byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Decode a percent-encoded path header value
+fn decode_path(value: &str) -> Result<PathBuf> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| anyhow!("malformed percent-encoding at byte {}", i))?;
+            decoded.push(hex);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(PathBuf::from(os_string_from_bytes(decoded)?))
+}
+
+#[cfg(unix)]
+fn os_string_from_bytes(bytes: Vec<u8>) -> Result<OsString> {
+    use std::os::unix::ffi::OsStringExt;
+    Ok(OsString::from_vec(bytes))
+}
+
+/// Decode WTF-8 (UTF-8 that may contain unpaired surrogates) into a wide string
+#[cfg(windows)]
+fn os_string_from_bytes(bytes: Vec<u8>) -> Result<OsString> {
+    use std::os::windows::ffi::OsStringExt;
+
+    let mut wide = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let lead = bytes[i];
+        let (len, init) = match lead {
+            0x00..=0x7F => (1, lead as u32),
+            0xC2..=0xDF => (2, (lead & 0x1F) as u32),
+            0xE0..=0xEF => (3, (lead & 0x0F) as u32),
+            0xF0..=0xF4 => (4, (lead & 0x07) as u32),
+            _ => bail!("invalid WTF-8 at byte {}", i),
+        };
+        let tail = bytes.get(i + 1..i + len).ok_or_else(|| anyhow!("truncated WTF-8 at byte {}", i))?;
+        let mut code_point = init;
+        for &byte in tail {
+            if byte & 0xC0 != 0x80 {
+                bail!("invalid WTF-8 at byte {}", i);
+            }
+            code_point = (code_point << 6) | (byte & 0x3F) as u32;
+        }
+        if code_point > 0xFFFF {
+            let c = code_point - 0x10000;
+            wide.push(0xD800 | (c >> 10) as u16);
+            wide.push(0xDC00 | (c & 0x3FF) as u16);
+        } else {
+            wide.push(code_point as u16);
+        }
+        i += len;
+    }
+    Ok(OsString::from_wide(&wide))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn abs(path: &str) -> PathBuf {
+        if cfg!(windows) { PathBuf::from(format!("C:{}", path)) } else { PathBuf::from(path) }
+    }
+
+    #[test]
+    fn test_round_trip_with_special_characters() {
+        let context = InfraContext::new(
+            "conn-42",
+            abs("/work/my repo/100%/ünïcode"),
+            Some(abs("/work/my repo")),
+        )
+        .unwrap();
+
+        let headers = context.to_headers();
+        assert!(headers.iter().all(|(_, v)| v.bytes().all(|b| b.is_ascii_graphic())), "{:?}", headers);
+        assert!(headers[1].1.ends_with("/my%20repo/100%25/%C3%BCn%C3%AFcode"), "{}", headers[1].1);
+
+        // Header names are case-insensitive
+        let upper: Vec<(String, String)> = headers.iter().map(|(n, v)| (n.to_uppercase(), v.clone())).collect();
+        let parsed = InfraContext::from_headers(upper.iter().map(|(n, v)| (n.as_str(), v.as_str()))).unwrap();
+        assert_eq!(parsed, context);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_round_trip_non_utf8_path() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let pwd = PathBuf::from(OsStr::from_bytes(b"/tmp/\xFF\xFEbad"));
+        let context = InfraContext::new("c", pwd, None).unwrap();
+        let headers = context.to_headers();
+        let parsed = InfraContext::from_headers(headers.iter().map(|(n, v)| (*n, v.as_str()))).unwrap();
+        assert_eq!(parsed, context);
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(InfraContext::new("c", PathBuf::from("relative"), None).is_err());
+        assert!(InfraContext::new("c", abs("/repo/../etc"), Some(abs("/repo"))).is_err());
+        assert!(InfraContext::new("c", abs("/elsewhere"), Some(abs("/repo"))).is_err());
+        assert!(InfraContext::new("c", abs("/repository"), Some(abs("/repo"))).is_err());
+        assert!(InfraContext::new("has space", abs("/repo"), None).is_err());
+        assert!(InfraContext::new("c", abs("/repo"), Some(abs("/repo"))).is_ok());
+
+        let missing = InfraContext::from_headers([(X_KODEGEN_CONNECTION_ID, "c")]).unwrap_err();
+        assert!(missing.to_string().contains(X_KODEGEN_PWD), "{}", missing);
+        assert!(decode_path("/bad%2").is_err());
+    }
+}
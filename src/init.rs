@@ -1,180 +1,1258 @@
-use anyhow::{anyhow, Result};
+//! Directory structure initialization
+//!
+//! Initialization is split into two phases so callers can see exactly what
+//! happens before anything touches the filesystem:
+//!
+//! 1. [`InitPlan::build()`] lists every action: directories to create, files
+//!    to write and `.gitignore` edits.
+//! 2. [`InitPlan::execute()`] applies the actions in order and returns an
+//!    [`InitReport`] recording, for each action, whether it was done, skipped
+//!    (and why) or failed. Execution stops at the first failure so the report
+//!    shows exactly where it stopped.
+//!
+//! In repair mode ([`InitOptions::repair`], the basis of `kodegen doctor --fix`)
+//! the plan checks the full expected layout. Files or dangling symlinks that
+//! occupy an expected directory are moved to `{root}/.quarantine/{timestamp}/`,
+//! execution continues past failures, and problems that cannot be fixed
+//! automatically are listed in [`InitReport::problems`].
+
+use anyhow::{anyhow, bail, Context, Result};
 use ignore::gitignore::GitignoreBuilder;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tempfile::NamedTempFile;
 
-/// Initialize directory structures for both local and user config
-pub fn create_directory_structure() -> Result<()> {
-    create_user_structure()?;
-    // Create local structure only if in git repo (ignore error if not)
-    if let Ok(local_dir) = crate::KodegenConfig::local_config_dir() {
-        create_local_structure(&local_dir)?;
+/// Contents of the `.gitignore` written to the user config root
+const USER_GITIGNORE: &str = "*.log\n*.log.gz\n*.tmp\n*.cache\n";
+
+/// Entry added to the repository `.gitignore`
+const LOCAL_GITIGNORE_ENTRY: &str = ".kodegen/";
+
+/// Entry added to the repository `.gitignore` with the shared layout
+const PRIVATE_GITIGNORE_ENTRY: &str = ".kodegen/local/";
+
+/// Shared config that must stay tracked with the shared layout
+const SHARED_TRACKED_PATHS: &[&str] = &[".kodegen/toolset"];
+
+/// Record of the ignore rules init added, stored next to `info/exclude`
+const IGNORE_RULES_RECORD: &str = "kodegen-ignore-rules.json";
+
+/// Directory (under a config root) receiving files moved aside by repair mode
+pub const QUARANTINE_DIR: &str = ".quarantine";
+
+/// Where init writes the rule ignoring `.kodegen/`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IgnoreFile {
+    /// `.git/info/exclude` (the common dir's for worktrees): not tracked, leaves no diff
+    #[default]
+    Exclude,
+    /// The repository's tracked `.gitignore`
+    Gitignore,
+}
+
+/// Options for [`init_structure_with()`](crate::KodegenConfig::init_structure_with)
+#[derive(Debug, Clone)]
+pub struct InitOptions {
+    /// Build and evaluate the plan without changing anything
+    pub dry_run: bool,
+    /// Initialize the user-global tree (`user_config_dir()`)
+    pub user: bool,
+    /// Initialize the git-local `.kodegen/` tree (if in a git repository)
+    pub local: bool,
+    /// Add an ignore rule for `.kodegen/` (see [`ignore_file`](Self::ignore_file))
+    pub gitignore: bool,
+    /// File receiving the ignore rule
+    pub ignore_file: IgnoreFile,
+    /// Write JSON Schemas and add `$schema` references to existing files
+    pub schemas: bool,
+    /// Move legacy user config roots first (see `migrate_legacy_locations()`)
+    ///
+    /// Off by default: moving whole config trees is never a side effect of init.
+    pub migrate_legacy: bool,
+    /// Check the full layout, quarantine conflicting files and keep going after failures
+    pub repair: bool,
+    /// Scaffold `.kodegen/` from this template (built-in or `user_config_dir()/templates/`)
+    pub template: Option<String>,
+    /// Overwrite existing files when scaffolding from a template
+    pub force: bool,
+    /// Commit `.kodegen/` and ignore only the private `.kodegen/local/` part
+    pub shared: bool,
+}
+
+impl Default for InitOptions {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            user: true,
+            local: true,
+            gitignore: true,
+            ignore_file: IgnoreFile::Exclude,
+            schemas: true,
+            migrate_legacy: false,
+            repair: false,
+            template: None,
+            force: false,
+            shared: false,
+        }
     }
-    Ok(())
 }
 
-/// Create user-global directory structure
-fn create_user_structure() -> Result<()> {
-    let config_dir = crate::KodegenConfig::user_config_dir()?;
-    let state_dir = crate::KodegenConfig::state_dir()?;
-    let data_dir = crate::KodegenConfig::data_dir()?;
-    let log_dir = crate::KodegenConfig::log_dir()?;
-    let cache_dir = crate::KodegenConfig::cache_dir()?;
+/// A single initialization step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitAction {
+    /// Move legacy user config roots into the current root
+    MigrateLegacy {
+        /// Legacy roots detected when the plan was built
+        sources: Vec<PathBuf>,
+        /// Current user config root
+        destination: PathBuf,
+    },
+    /// Move a file that blocks the expected layout out of the way (repair mode)
+    Quarantine {
+        /// Conflicting file, symlink or directory
+        path: PathBuf,
+        /// Where it is moved to
+        destination: PathBuf,
+    },
+    /// Create a directory (and missing parents)
+    CreateDir {
+        /// Directory to create
+        path: PathBuf,
+    },
+    /// Write a file
+    WriteFile {
+        /// File to write
+        path: PathBuf,
+        /// Full file contents
+        contents: String,
+        /// Replace an existing file whose contents differ
+        overwrite: bool,
+    },
+    /// Insert a `$schema` reference into an existing JSON file
+    AddSchemaReference {
+        /// File to edit
+        path: PathBuf,
+        /// Relative schema path to reference
+        reference: String,
+    },
+    /// Append an entry to a `.gitignore` or `info/exclude` file unless already ignored
+    GitignoreEntry {
+        /// Gitignore or exclude file to edit
+        gitignore: PathBuf,
+        /// Repository root the patterns are relative to
+        git_root: PathBuf,
+        /// Pattern to add
+        entry: String,
+        /// Paths (relative to `git_root`) the rules must leave unignored
+        tracked: Vec<String>,
+    },
+    /// Remove an entry previously added to a `.gitignore` or `info/exclude` file (teardown)
+    RemoveGitignoreEntry {
+        /// Gitignore or exclude file to edit
+        gitignore: PathBuf,
+        /// Repository whose record of added rules is updated
+        git_root: PathBuf,
+        /// Exact line to remove
+        entry: String,
+    },
+    /// Recursively delete a directory (teardown)
+    RemoveDir {
+        /// Directory to delete
+        path: PathBuf,
+    },
+}
+
+impl std::fmt::Display for InitAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MigrateLegacy { sources, destination } => write!(
+                f,
+                "migrate legacy config {} -> {}",
+                sources.iter().map(|s| s.display().to_string()).collect::<Vec<_>>().join(", "),
+                destination.display()
+            ),
+            Self::Quarantine { path, destination } => {
+                write!(f, "quarantine {} -> {}", path.display(), destination.display())
+            }
+            Self::CreateDir { path } => write!(f, "create directory {}", path.display()),
+            Self::WriteFile { path, .. } => write!(f, "write file {}", path.display()),
+            Self::AddSchemaReference { path, reference } => {
+                write!(f, "add $schema \"{}\" to {}", reference, path.display())
+            }
+            Self::GitignoreEntry { gitignore, entry, .. } => {
+                write!(f, "add '{}' to {}", entry, gitignore.display())
+            }
+            Self::RemoveGitignoreEntry { gitignore, entry, .. } => {
+                write!(f, "remove '{}' from {}", entry, gitignore.display())
+            }
+            Self::RemoveDir { path } => write!(f, "remove directory {}", path.display()),
+        }
+    }
+}
 
-    // Create config subdirectories
-    fs::create_dir_all(config_dir.join("toolset"))?;
-    fs::create_dir_all(config_dir.join("claude"))?;
+impl InitAction {
+    /// Reason this action does not need to run, if any
+    fn skip_reason(&self) -> Result<Option<String>> {
+        Ok(match self {
+            Self::MigrateLegacy { destination, .. } => {
+                if destination.join(crate::legacy::LEGACY_MIGRATION_MARKER).exists() {
+                    Some("legacy migration already recorded".to_string())
+                } else {
+                    None
+                }
+            }
+            Self::Quarantine { path, .. } => (fs::symlink_metadata(path).is_err())
+                .then(|| "conflicting path no longer exists".to_string()),
+            Self::CreateDir { path } => path.is_dir().then(|| "directory already exists".to_string()),
+            Self::WriteFile { path, contents, overwrite } => match fs::read_to_string(path) {
+                Ok(existing) if existing == *contents => Some("file is up to date".to_string()),
+                Ok(_) if !overwrite => Some("file already exists".to_string()),
+                _ => None,
+            },
+            Self::AddSchemaReference { path, .. } => (!crate::schema::needs_schema_reference(path))
+                .then(|| "file already declares $schema or is not a JSON object".to_string()),
+            Self::GitignoreEntry { gitignore, git_root, entry, tracked } => {
+                // Tracked paths that are ignored are reported as a failure by apply()
+                (is_ignored(git_root, gitignore, entry)? && ignored_tracked_path(git_root, gitignore, tracked)?.is_none())
+                    .then(|| format!("{} is already ignored by {}", entry, gitignore.display()))
+            }
+            Self::RemoveGitignoreEntry { gitignore, entry, .. } => (!remove_gitignore_entry(gitignore, entry, true)?)
+                .then(|| format!("{} does not contain '{}'", gitignore.display(), entry)),
+            Self::RemoveDir { path } => {
+                (fs::symlink_metadata(path).is_err()).then(|| "directory does not exist".to_string())
+            }
+        })
+    }
 
-    // Create state directory (for PIDs, sockets, runtime state)
-    fs::create_dir_all(&state_dir)?;
+    /// Apply the action
+    fn apply(&self) -> Result<()> {
+        match self {
+            Self::MigrateLegacy { sources, destination } => {
+                crate::legacy::migrate_roots(destination, sources, &Default::default())?;
+            }
+            Self::Quarantine { path, destination } => {
+                // rename() would silently replace an existing file
+                if fs::symlink_metadata(destination).is_ok() {
+                    bail!("quarantine destination already exists: {}", destination.display());
+                }
+                if let Some(parent) = destination.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::rename(path, destination)?;
+                log::warn!("Quarantined {} to {}", path.display(), destination.display());
+            }
+            Self::CreateDir { path } => fs::create_dir_all(path)?,
+            Self::WriteFile { path, contents, .. } => {
+                let dir = path
+                    .parent()
+                    .ok_or_else(|| anyhow!("File has no parent directory: {}", path.display()))?;
+                fs::create_dir_all(dir)?;
+                let mut temp_file = NamedTempFile::new_in(dir)?;
+                temp_file.write_all(contents.as_bytes())?;
+                temp_file.persist(path)?;
+            }
+            Self::AddSchemaReference { path, reference } => {
+                crate::schema::add_schema_reference(path, reference)?;
+            }
+            Self::GitignoreEntry { gitignore, git_root, entry, tracked } => {
+                append_gitignore_entry(git_root, gitignore, entry)?;
+                if let Some(path) = ignored_tracked_path(git_root, gitignore, tracked)? {
+                    return Err(anyhow!(
+                        "{} ignores '{}', which should be committed with the shared layout. \
+                         Replace the rule ignoring it with '{}'",
+                        gitignore.display(),
+                        path,
+                        entry
+                    ));
+                }
+            }
+            Self::RemoveGitignoreEntry { gitignore, git_root, entry } => {
+                remove_gitignore_entry(gitignore, entry, false)?;
+                let mut record = IgnoreRulesRecord::read(git_root)?;
+                record.rules.retain(|rule| !(rule.file == *gitignore && rule.entry == *entry));
+                record.write(git_root)?;
+            }
+            Self::RemoveDir { path } => {
+                // remove_dir_all() removes a symlink itself rather than its target
+                if fs::symlink_metadata(path)?.file_type().is_symlink() {
+                    fs::remove_file(path)?;
+                } else {
+                    fs::remove_dir_all(path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
 
-    // Create log directory (for .log files)
-    fs::create_dir_all(&log_dir)?;
+/// Outcome of a single [`InitAction`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitOutcome {
+    /// The action was applied
+    Done,
+    /// The action would be applied (dry-run)
+    Planned,
+    /// The action was not needed or not attempted
+    Skipped {
+        /// Why the action was skipped
+        reason: String,
+    },
+    /// The action failed; later actions were not attempted
+    Failed {
+        /// Error message
+        error: String,
+    },
+}
 
-    // Create data subdirectories
-    fs::create_dir_all(data_dir.join("stats"))?;
-    fs::create_dir_all(data_dir.join("memory"))?;
+/// An action paired with its outcome
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitReportEntry {
+    /// The action from the plan
+    pub action: InitAction,
+    /// What happened to it
+    pub outcome: InitOutcome,
+}
 
-    // Create cache directory (for temporary build artifacts, downloads)
-    fs::create_dir_all(&cache_dir)?;
+/// A layout problem that repair mode cannot fix automatically
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitProblem {
+    /// Affected path
+    pub path: PathBuf,
+    /// What is wrong and what to do about it
+    pub problem: String,
+}
 
-    // Create .gitignore if it doesn't exist
-    let gitignore_path = config_dir.join(".gitignore");
-    if !gitignore_path.exists() {
-        fs::write(gitignore_path, "*.log\n*.tmp\n*.cache\n")?;
+impl std::fmt::Display for InitProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.problem)
     }
+}
 
-    Ok(())
+/// Result of executing an [`InitPlan`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InitReport {
+    /// Whether this was a dry-run
+    pub dry_run: bool,
+    /// One entry per planned action, in execution order
+    pub entries: Vec<InitReportEntry>,
+    /// Problems found while planning that need manual attention
+    pub problems: Vec<InitProblem>,
 }
 
-/// Create local .kodegen directory structure
-fn create_local_structure(local_dir: &Path) -> Result<()> {
-    // Validate input: local_dir should end with ".kodegen"
-    if local_dir.file_name() != Some(std::ffi::OsStr::new(".kodegen")) {
-        log::warn!(
-            "Unexpected local_dir path structure: {}. Expected path ending with '.kodegen'",
-            local_dir.display()
+impl InitReport {
+    /// Whether every action succeeded or was skipped and no problems were found
+    pub fn is_success(&self) -> bool {
+        self.failure().is_none() && self.problems.is_empty()
+    }
+
+    /// The failed entry, if execution stopped early
+    pub fn failure(&self) -> Option<&InitReportEntry> {
+        self.entries
+            .iter()
+            .find(|entry| matches!(entry.outcome, InitOutcome::Failed { .. }))
+    }
+
+    /// Actions that were applied (or would be, for a dry-run)
+    pub fn applied(&self) -> impl Iterator<Item = &InitAction> {
+        self.entries
+            .iter()
+            .filter(|entry| matches!(entry.outcome, InitOutcome::Done | InitOutcome::Planned))
+            .map(|entry| &entry.action)
+    }
+
+    /// Convert a report containing a failure into an error
+    pub fn into_result(self) -> Result<Self> {
+        if let Some(InitReportEntry { action, outcome: InitOutcome::Failed { error } }) = self.failure() {
+            return Err(anyhow!("Initialization failed at '{}': {}", action, error));
+        }
+        if let Some(problem) = self.problems.first() {
+            return Err(anyhow!("Initialization found a problem it cannot fix: {}", problem));
+        }
+        Ok(self)
+    }
+}
+
+impl std::fmt::Display for InitReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for entry in &self.entries {
+            match &entry.outcome {
+                InitOutcome::Done => writeln!(f, "done     {}", entry.action)?,
+                InitOutcome::Planned => writeln!(f, "planned  {}", entry.action)?,
+                InitOutcome::Skipped { reason } => writeln!(f, "skipped  {} ({})", entry.action, reason)?,
+                InitOutcome::Failed { error } => writeln!(f, "FAILED   {}: {}", entry.action, error)?,
+            }
+        }
+        for problem in &self.problems {
+            writeln!(f, "problem  {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+/// Ordered list of initialization actions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InitPlan {
+    /// Actions in execution order
+    pub actions: Vec<InitAction>,
+    /// Problems found while planning that no action can fix
+    pub problems: Vec<InitProblem>,
+    /// Continue with later actions after a failure (repair mode)
+    pub keep_going: bool,
+}
+
+impl InitPlan {
+    /// Build the plan for the given options
+    ///
+    /// Building inspects the filesystem (legacy roots, existing toolset files)
+    /// but never modifies it.
+    pub fn build(options: &InitOptions) -> Result<Self> {
+        let mut plan = Self { keep_going: options.repair, ..Self::default() };
+
+        if options.user {
+            if options.migrate_legacy {
+                let destination = crate::KodegenConfig::user_config_dir()?;
+                let sources = crate::legacy::detect(&destination, &crate::legacy::candidate_roots());
+                if !sources.is_empty() {
+                    plan.actions.push(InitAction::MigrateLegacy { sources, destination });
+                }
+            }
+            plan.add_user_structure(options)?;
+        }
+
+        if options.local
+            && let Ok(local_dir) = crate::KodegenConfig::local_config_dir()
+        {
+            plan.add_local_structure(&local_dir, options)?;
+        }
+
+        Ok(plan)
+    }
+
+    /// Apply the plan (or evaluate it, for a dry-run)
+    ///
+    /// Never returns an error for a failed action; check
+    /// [`InitReport::is_success()`] or use [`InitReport::into_result()`].
+    pub fn execute(&self, dry_run: bool) -> InitReport {
+        let mut report = InitReport {
+            dry_run,
+            entries: Vec::with_capacity(self.actions.len()),
+            problems: self.problems.clone(),
+        };
+        let mut failed = false;
+
+        for action in &self.actions {
+            let outcome = if failed && !self.keep_going {
+                InitOutcome::Skipped { reason: "not attempted: an earlier action failed".to_string() }
+            } else {
+                match action.skip_reason() {
+                    Ok(Some(reason)) => InitOutcome::Skipped { reason },
+                    Ok(None) if dry_run => InitOutcome::Planned,
+                    Ok(None) => match action.apply() {
+                        Ok(()) => InitOutcome::Done,
+                        Err(e) => InitOutcome::Failed { error: format!("{:#}", e) },
+                    },
+                    Err(e) => InitOutcome::Failed { error: format!("{:#}", e) },
+                }
+            };
+            failed |= matches!(outcome, InitOutcome::Failed { .. });
+            report.entries.push(InitReportEntry { action: action.clone(), outcome });
+        }
+
+        report
+    }
+
+    /// Plan the user-global directory structure
+    fn add_user_structure(&mut self, options: &InitOptions) -> Result<()> {
+        let config_dir = crate::KodegenConfig::user_config_dir()?;
+
+        for path in user_layout()? {
+            self.add_dir(&config_dir, path, options.repair);
+        }
+
+        if options.schemas {
+            self.add_schemas(&config_dir, options.repair)?;
+        }
+
+        self.add_file(
+            &config_dir,
+            config_dir.join(".gitignore"),
+            USER_GITIGNORE.to_string(),
+            false,
+            options.repair,
         );
+
+        Ok(())
     }
 
-    // Create .kodegen subdirectories
-    fs::create_dir_all(local_dir.join("toolset"))?;
-    fs::create_dir_all(local_dir.join("claude"))?;
+    /// Plan a directory, quarantining whatever blocks it in repair mode
+    fn add_dir(&mut self, root: &Path, path: PathBuf, repair: bool) {
+        if repair {
+            self.check_blockers(root, &path, true);
+        }
+        self.actions.push(InitAction::CreateDir { path });
+    }
 
-    // Git root must be parent of .kodegen - use ok_or_else pattern
-    let git_root = local_dir.parent().ok_or_else(|| {
-        anyhow!(
-            "Cannot determine git root: local_dir has no parent directory ({}). \
-             This indicates a bug in git repository discovery",
-            local_dir.display()
-        )
-    })?;
+    /// Plan a file, quarantining whatever blocks it in repair mode
+    fn add_file(&mut self, root: &Path, path: PathBuf, contents: String, overwrite: bool, repair: bool) {
+        if repair {
+            self.check_blockers(root, &path, false);
+        }
+        self.actions.push(InitAction::WriteFile { path, contents, overwrite });
+    }
 
-    add_to_gitignore(git_root)?;
+    /// Find the first path component under `root` that has the wrong type
+    ///
+    /// Ancestors of `path` must be directories; `path` itself must be a
+    /// directory if `want_dir`, otherwise a regular file (or missing).
+    fn check_blockers(&mut self, root: &Path, path: &Path, want_dir: bool) {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return;
+        };
+        let components: Vec<_> = relative.components().collect();
 
-    Ok(())
+        // The root itself cannot be quarantined into itself
+        let mut candidates = vec![(root.to_path_buf(), true)];
+        let mut current = root.to_path_buf();
+        for (index, component) in components.iter().enumerate() {
+            current.push(component);
+            let is_last = index + 1 == components.len();
+            candidates.push((current.clone(), !is_last || want_dir));
+        }
+
+        for (index, (candidate, should_be_dir)) in candidates.into_iter().enumerate() {
+            let Ok(metadata) = fs::symlink_metadata(&candidate) else {
+                // Missing - everything below will be created
+                return;
+            };
+            // Symlinks are fine as long as they resolve to the right type
+            let resolved = fs::metadata(&candidate).ok();
+            let ok = match &resolved {
+                Some(m) if should_be_dir => m.is_dir(),
+                Some(m) => m.is_file(),
+                None => false,
+            };
+
+            if ok {
+                if should_be_dir
+                    && resolved.as_ref().is_some_and(|m| m.permissions().readonly())
+                    && !self.problems.iter().any(|p| p.path == candidate)
+                {
+                    self.problems.push(InitProblem {
+                        path: candidate.clone(),
+                        problem: "directory is read-only; fix its permissions".to_string(),
+                    });
+                }
+                continue;
+            }
+
+            if index == 0 {
+                if !self.problems.iter().any(|p| p.path == candidate) {
+                    self.problems.push(InitProblem {
+                        path: candidate,
+                        problem: "config root is not a directory; move it aside manually".to_string(),
+                    });
+                }
+                return;
+            }
+
+            let already_planned = self
+                .actions
+                .iter()
+                .any(|a| matches!(a, InitAction::Quarantine { path, .. } if *path == candidate));
+            if !already_planned {
+                let kind = if metadata.file_type().is_symlink() { "symlink" } else if metadata.is_dir() { "directory" } else { "file" };
+                log::warn!("Repair: {} {} blocks the expected layout", kind, candidate.display());
+                let destination = self.quarantine_dir(root).join(candidate.strip_prefix(root).unwrap_or(&candidate));
+                self.actions.push(InitAction::Quarantine { path: candidate, destination });
+            }
+            return;
+        }
+    }
+
+    /// Quarantine batch directory for `root`, shared by all quarantines in this plan
+    fn quarantine_dir(&self, root: &Path) -> PathBuf {
+        let quarantine = root.join(QUARANTINE_DIR);
+        self.actions
+            .iter()
+            .find_map(|action| match action {
+                InitAction::Quarantine { destination, .. } => destination
+                    .strip_prefix(&quarantine)
+                    .ok()
+                    .and_then(|relative| relative.components().next())
+                    .map(|batch| quarantine.join(batch)),
+                _ => None,
+            })
+            .unwrap_or_else(|| new_quarantine_dir(root))
+    }
+
+    /// Plan the local .kodegen directory structure
+    fn add_local_structure(&mut self, local_dir: &Path, options: &InitOptions) -> Result<()> {
+        // Validate input: local_dir should end with ".kodegen"
+        if local_dir.file_name() != Some(std::ffi::OsStr::new(".kodegen")) {
+            log::warn!(
+                "Unexpected local_dir path structure: {}. Expected path ending with '.kodegen'",
+                local_dir.display()
+            );
+        }
+
+        // Git root must be parent of .kodegen - use ok_or_else pattern
+        let git_root = local_dir.parent().ok_or_else(|| {
+            anyhow!(
+                "Cannot determine git root: local_dir has no parent directory ({}). \
+                 This indicates a bug in git repository discovery",
+                local_dir.display()
+            )
+        })?;
+
+        self.add_dir(local_dir, local_dir.join("toolset"), options.repair);
+        self.add_dir(local_dir, local_dir.join("claude"), options.repair);
+        self.add_dir(local_dir, local_dir.join(crate::LOCAL_PRIVATE_DIR), options.repair);
+
+        if let Some(template) = &options.template {
+            let vars = crate::templates::TemplateVars::for_git_root(git_root);
+            for (path, contents) in crate::templates::render(template, local_dir, &vars)? {
+                self.add_file(local_dir, path, contents, options.force, options.repair);
+            }
+        }
+
+        if options.schemas {
+            self.add_schemas(local_dir, options.repair)?;
+        }
+
+        if options.gitignore {
+            let (entry, tracked) = if options.shared {
+                (PRIVATE_GITIGNORE_ENTRY, SHARED_TRACKED_PATHS.iter().map(|p| p.to_string()).collect())
+            } else {
+                (LOCAL_GITIGNORE_ENTRY, Vec::new())
+            };
+            let gitignore = match options.ignore_file {
+                IgnoreFile::Exclude => crate::git::info_exclude_path(git_root)?,
+                IgnoreFile::Gitignore => git_root.join(".gitignore"),
+            };
+            self.actions.push(InitAction::GitignoreEntry {
+                gitignore,
+                git_root: git_root.to_path_buf(),
+                entry: entry.to_string(),
+                tracked,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Plan JSON Schema files and `$schema` references under a config root
+    fn add_schemas(&mut self, root: &Path, repair: bool) -> Result<()> {
+        for (path, contents) in crate::schema::schema_files(root)? {
+            self.add_file(root, path, contents, true, repair);
+        }
+        for (path, reference) in crate::schema::schema_reference_targets(root) {
+            self.actions.push(InitAction::AddSchemaReference { path, reference });
+        }
+        Ok(())
+    }
 }
 
-/// Add .kodegen to .gitignore if not already present
+/// Expected user-global directories
 ///
-/// Uses semantic gitignore pattern matching to detect if .kodegen is already
-/// ignored by any pattern (e.g., `.kodegen/`, `**/.kodegen/`, `/.kodegen/`).
-/// 
-/// This prevents false positives from substring matches against comments,
-/// similar directory names, or unrelated patterns.
+/// `toolset/`, `claude/`, `state/`, `logs/`, `data/stats/`, `data/memory/`,
+/// `cache/` and `bin/` under the user config root.
+fn user_layout() -> Result<Vec<PathBuf>> {
+    let config_dir = crate::KodegenConfig::user_config_dir()?;
+    let data_dir = crate::KodegenConfig::data_dir()?;
+
+    Ok(vec![
+        // Config subdirectories
+        crate::KodegenConfig::toolset_dir()?,
+        config_dir.join("claude"),
+        // State directory (for PIDs, sockets, runtime state)
+        crate::KodegenConfig::state_dir()?,
+        // Log directory (for .log files)
+        crate::KodegenConfig::log_dir()?,
+        // Data subdirectories
+        data_dir.join("stats"),
+        data_dir.join("memory"),
+        // Cache directory (for temporary build artifacts, downloads)
+        crate::KodegenConfig::cache_dir()?,
+        // Binary directory (for binary storage before symlinking)
+        crate::KodegenConfig::bin_dir()?,
+    ])
+}
+
+/// New quarantine batch directory under `root`
+///
+/// `{root}/.quarantine/{timestamp}`, with microsecond precision and a `-N`
+/// suffix if that directory already exists, so repairs never share a batch.
+fn new_quarantine_dir(root: &Path) -> PathBuf {
+    let quarantine = root.join(QUARANTINE_DIR);
+    let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%.6fZ").to_string();
+    let mut dir = quarantine.join(&timestamp);
+    let mut suffix = 2;
+    while fs::symlink_metadata(&dir).is_ok() {
+        dir = quarantine.join(format!("{}-{}", timestamp, suffix));
+        suffix += 1;
+    }
+    dir
+}
+
+/// Initialize directory structures with options, returning a report
+pub fn create_directory_structure_with(options: &InitOptions) -> Result<InitReport> {
+    let plan = InitPlan::build(options)?;
+    Ok(plan.execute(options.dry_run))
+}
+
+/// Initialize directory structures for both local and user config
+pub fn create_directory_structure() -> Result<()> {
+    create_directory_structure_with(&InitOptions::default())?.into_result()?;
+    Ok(())
+}
+
+/// Verify an ignore file (`.gitignore` or `info/exclude`) is safe to read and modify
 ///
 /// Security: This function explicitly rejects symbolic links to prevent
-/// arbitrary file read/write attacks (CWE-61). It uses atomic writes
-/// via temporary files to prevent race conditions (CWE-362).
-fn add_to_gitignore(git_root: &Path) -> Result<()> {
-    let gitignore_path = git_root.join(".gitignore");
-    
+/// arbitrary file read/write attacks (CWE-61).
+fn check_gitignore_file(gitignore_path: &Path) -> Result<()> {
+    let name = gitignore_path.file_name().unwrap_or(gitignore_path.as_os_str()).to_string_lossy();
+
     // SECURITY: Check if .gitignore exists and verify it's not a symlink
     // Using symlink_metadata() instead of metadata() - crucial difference:
     // - symlink_metadata() does NOT follow symlinks (uses lstat on Unix)
     // - metadata() DOES follow symlinks (uses stat on Unix)
-    if gitignore_path.exists() {
-        let metadata = fs::symlink_metadata(&gitignore_path)?;
-        
+    if gitignore_path.exists() || gitignore_path.is_symlink() {
+        let metadata = fs::symlink_metadata(gitignore_path)?;
+
         // Reject symbolic links
         if metadata.file_type().is_symlink() {
             // Log security event
             log::warn!(
-                "Security: Refusing to modify .gitignore - it is a symbolic link: {}",
+                "Security: Refusing to modify {} - it is a symbolic link: {}",
+                name,
                 gitignore_path.display()
             );
-            
+
             return Err(anyhow::anyhow!(
-                "Security: .gitignore is a symbolic link (refusing to modify): {}\n\
+                "Security: {} is a symbolic link (refusing to modify): {}\n\
                  Remove the symlink and create a regular file instead.",
+                name,
                 gitignore_path.display()
             ));
         }
-        
+
         // Reject non-regular files (directories, devices, etc.)
         if !metadata.file_type().is_file() {
             return Err(anyhow::anyhow!(
-                ".gitignore exists but is not a regular file: {}",
+                "{} exists but is not a regular file: {}",
+                name,
                 gitignore_path.display()
             ));
         }
     }
-    
-    // Read existing content (now safe - we verified it's a regular file)
-    let content = if gitignore_path.exists() {
-        fs::read_to_string(&gitignore_path)?
-    } else {
-        String::new()
-    };
-    
-    // Build gitignore matcher from existing .gitignore file using semantic pattern matching
+    Ok(())
+}
+
+/// Build a matcher from a gitignore file plus the repository's other ignore sources
+///
+/// Rules from `info/exclude` and the root `.gitignore` are included so an
+/// entry in either one counts, whichever file is being edited. They are added
+/// in git's precedence order (later files win).
+fn gitignore_matcher(git_root: &Path, gitignore_path: &Path) -> Result<ignore::gitignore::Gitignore> {
+    check_gitignore_file(gitignore_path)?;
+
+    let mut sources = Vec::new();
+    if let Ok(exclude) = crate::git::info_exclude_path(git_root) {
+        sources.push(exclude);
+    }
+    sources.push(git_root.join(".gitignore"));
+    if !sources.iter().any(|p| p == gitignore_path) {
+        sources.push(gitignore_path.to_path_buf());
+    }
+
+    // Build gitignore matcher from existing files using semantic pattern matching
     let mut builder = GitignoreBuilder::new(git_root);
-    if gitignore_path.exists() {
-        builder.add(&gitignore_path);
+    for source in sources.iter().filter(|p| p.is_file()) {
+        builder.add(source);
     }
-    let gitignore = builder.build()?;
-    
-    // Test if .kodegen directory would be ignored using semantic pattern matching
-    // We test a hypothetical file inside .kodegen to see if the directory is ignored
+    Ok(builder.build()?)
+}
+
+/// Check whether a directory entry such as `.kodegen/` is already ignored by a gitignore file
+///
+/// Uses semantic gitignore pattern matching to detect if the directory is already
+/// ignored by any pattern (e.g., `.kodegen/`, `**/.kodegen/`, `/.kodegen/`).
+///
+/// This prevents false positives from substring matches against comments,
+/// similar directory names, or unrelated patterns.
+fn is_ignored(git_root: &Path, gitignore_path: &Path, entry: &str) -> Result<bool> {
+    let gitignore = gitignore_matcher(git_root, gitignore_path)?;
+
+    // Test if the directory would be ignored using semantic pattern matching
+    // We test a hypothetical file inside it to see if the directory is ignored.
+    // Parents must be checked too: `matched()` alone only tests the file itself,
+    // so directory patterns like `.kodegen/` would never match.
     // This correctly handles all gitignore pattern variations:
     // - .kodegen/ (exact match)
     // - .kodegen (without trailing slash)
     // - /.kodegen/ (root-only pattern)
     // - **/.kodegen/ (any subdirectory)
     // - .kodegen/** (everything inside .kodegen)
-    let test_path = git_root.join(".kodegen/test.txt");
-    let is_ignored = gitignore.matched(&test_path, false).is_ignore();
-    
-    // Only add .kodegen/ entry if it's not already semantically ignored
-    if !is_ignored {
-        // Use atomic write pattern from kodegend/src/install/binary_staging.rs
-        // Create temporary file in the same directory as target
-        // This ensures atomic replacement and prevents partial writes
-        let mut temp_file = NamedTempFile::new_in(git_root)?;
-        
-        // Write existing content
-        temp_file.write_all(content.as_bytes())?;
-        
-        // Add newline before .kodegen entry if content doesn't end with one
-        if !content.is_empty() && !content.ends_with('\n') {
-            temp_file.write_all(b"\n")?;
-        }
-        
-        // Add .kodegen entry
-        temp_file.write_all(b".kodegen/\n")?;
-        
-        // Atomically replace .gitignore
-        // persist() performs atomic rename (mv on Unix, MoveFileEx on Windows)
-        // This prevents:
-        // - Race conditions (CWE-362)
-        // - Partial writes from crashes
-        // - TOCTOU (Time-of-check-time-of-use) vulnerabilities
-        temp_file.persist(&gitignore_path)?;
-        
-        log::info!("Added .kodegen/ to .gitignore: {}", gitignore_path.display());
-    }
-    
+    let test_path = git_root.join(entry.trim_matches('/')).join("test.txt");
+    Ok(gitignore.matched_path_or_any_parents(&test_path, false).is_ignore())
+}
+
+/// First of `tracked` (relative to `git_root`) that the gitignore file ignores
+///
+/// Each path is tested both as itself and as a directory containing a file,
+/// so it works for files like `ports.json` and directories like `toolset`.
+fn ignored_tracked_path(git_root: &Path, gitignore_path: &Path, tracked: &[String]) -> Result<Option<String>> {
+    if tracked.is_empty() {
+        return Ok(None);
+    }
+    let gitignore = gitignore_matcher(git_root, gitignore_path)?;
+
+    Ok(tracked
+        .iter()
+        .find(|path| {
+            let path = git_root.join(path);
+            gitignore.matched_path_or_any_parents(&path, false).is_ignore()
+                || gitignore.matched_path_or_any_parents(path.join("test.json"), false).is_ignore()
+        })
+        .cloned())
+}
+
+/// Add an entry to .gitignore if it is not already ignored
+///
+/// Security: This function explicitly rejects symbolic links to prevent
+/// arbitrary file read/write attacks (CWE-61). It uses atomic writes
+/// via temporary files to prevent race conditions (CWE-362).
+fn append_gitignore_entry(git_root: &Path, gitignore_path: &Path, entry: &str) -> Result<()> {
+    // Only add the entry if it is not already semantically ignored
+    if is_ignored(git_root, gitignore_path, entry)? {
+        return Ok(());
+    }
+
+    // Read existing content (now safe - we verified it's a regular file)
+    let content = if gitignore_path.exists() {
+        fs::read_to_string(gitignore_path)?
+    } else {
+        String::new()
+    };
+
+    // Use atomic write pattern from kodegend/src/install/binary_staging.rs
+    // Create temporary file in the same directory as target
+    // This ensures atomic replacement and prevents partial writes
+    // (`.git/info/` may not exist yet in a fresh repository)
+    let dir = gitignore_path.parent().unwrap_or(git_root);
+    fs::create_dir_all(dir)?;
+    let mut temp_file = NamedTempFile::new_in(dir)?;
+
+    // Write existing content
+    temp_file.write_all(content.as_bytes())?;
+
+    // Match the file's line endings
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+
+    // Add newline before the entry if content doesn't end with one
+    if !content.is_empty() && !content.ends_with('\n') {
+        temp_file.write_all(newline.as_bytes())?;
+    }
+
+    // Add the entry
+    temp_file.write_all(entry.as_bytes())?;
+    temp_file.write_all(newline.as_bytes())?;
+
+    // Atomically replace .gitignore
+    // persist() performs atomic rename (mv on Unix, MoveFileEx on Windows)
+    // This prevents:
+    // - Race conditions (CWE-362)
+    // - Partial writes from crashes
+    // - TOCTOU (Time-of-check-time-of-use) vulnerabilities
+    temp_file.persist(gitignore_path)?;
+
+    // Remember the line so teardown removes only what init added
+    let mut record = IgnoreRulesRecord::read(git_root)?;
+    let rule = RecordedRule { file: gitignore_path.to_path_buf(), entry: entry.to_string() };
+    if !record.rules.contains(&rule) {
+        record.rules.push(rule);
+        record.write(git_root)?;
+    }
+
+    log::info!("Added {} to ignore file: {}", entry, gitignore_path.display());
+
     Ok(())
 }
+
+/// Remove the last line matching `entry` exactly from a gitignore or exclude file
+///
+/// Init appends its entries, so the last match is the one it added. Returns
+/// whether the file changed. Uses the same symlink checks and atomic
+/// temp-file write as [`append_gitignore_entry()`].
+fn remove_gitignore_entry(gitignore_path: &Path, entry: &str, dry_run: bool) -> Result<bool> {
+    check_gitignore_file(gitignore_path)?;
+    if !gitignore_path.exists() {
+        return Ok(false);
+    }
+
+    // Lines keep their own terminators so CRLF files stay CRLF
+    let content = fs::read_to_string(gitignore_path)?;
+    let mut kept: Vec<&str> = content.split_inclusive('\n').collect();
+    let Some(index) = kept.iter().rposition(|line| line.trim() == entry) else {
+        return Ok(false);
+    };
+    kept.remove(index);
+    if dry_run {
+        return Ok(true);
+    }
+
+    let dir = gitignore_path
+        .parent()
+        .ok_or_else(|| anyhow!("File has no parent directory: {}", gitignore_path.display()))?;
+    let mut temp_file = NamedTempFile::new_in(dir)?;
+    for line in kept {
+        temp_file.write_all(line.as_bytes())?;
+    }
+    temp_file.persist(gitignore_path)?;
+
+    log::info!("Removed {} from ignore file: {}", entry, gitignore_path.display());
+    Ok(true)
+}
+
+/// An ignore rule line init appended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct RecordedRule {
+    file: PathBuf,
+    entry: String,
+}
+
+/// Ignore rules init added to a repository, kept in `{git dir}/info/`
+///
+/// Lines the user wrote themselves (even identical ones) are never recorded,
+/// so removing the recorded rules cannot un-ignore anything init didn't ignore.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IgnoreRulesRecord {
+    rules: Vec<RecordedRule>,
+}
+
+impl IgnoreRulesRecord {
+    fn path(git_root: &Path) -> Result<PathBuf> {
+        Ok(crate::git::info_exclude_path(git_root)?.with_file_name(IGNORE_RULES_RECORD))
+    }
+
+    fn read(git_root: &Path) -> Result<Self> {
+        let path = Self::path(git_root)?;
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid ignore rules record: {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    fn write(&self, git_root: &Path) -> Result<()> {
+        let path = Self::path(git_root)?;
+        if self.rules.is_empty() {
+            return match fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            };
+        }
+        let dir = path.parent().unwrap_or(git_root);
+        fs::create_dir_all(dir)?;
+        let mut temp_file = NamedTempFile::new_in(dir)?;
+        temp_file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        temp_file.persist(&path)?;
+        Ok(())
+    }
+}
+
+/// Plan removal of the ignore rules init added to `.gitignore` and `info/exclude`
+///
+/// Only rules recorded when init appended them, and still present in their
+/// file, get an action.
+pub(crate) fn plan_ignore_rule_removal(git_root: &Path) -> Result<Vec<InitAction>> {
+    let mut actions = Vec::new();
+    for rule in IgnoreRulesRecord::read(git_root)?.rules {
+        if remove_gitignore_entry(&rule.file, &rule.entry, true)? {
+            actions.push(InitAction::RemoveGitignoreEntry {
+                gitignore: rule.file,
+                git_root: git_root.to_path_buf(),
+                entry: rule.entry,
+            });
+        }
+    }
+    Ok(actions)
+}
+
+/// Remove the ignore rules init added to the repository's `.gitignore` and `info/exclude`
+///
+/// Returns the files that were (or, with `dry_run`, would be) changed.
+pub fn remove_ignore_rules(git_root: &Path, dry_run: bool) -> Result<Vec<PathBuf>> {
+    let plan = InitPlan { actions: plan_ignore_rule_removal(git_root)?, ..Default::default() };
+    let report = plan.execute(dry_run).into_result()?;
+
+    let mut changed: Vec<PathBuf> = report
+        .applied()
+        .filter_map(|action| match action {
+            InitAction::RemoveGitignoreEntry { gitignore, .. } => Some(gitignore.clone()),
+            _ => None,
+        })
+        .collect();
+    changed.dedup();
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir_action(path: &Path) -> InitAction {
+        InitAction::CreateDir { path: path.to_path_buf() }
+    }
+
+    #[test]
+    fn test_dry_run_changes_nothing() {
+        let temp = tempfile::tempdir().unwrap();
+        let plan = InitPlan {
+            actions: vec![
+                dir_action(&temp.path().join("a")),
+                InitAction::GitignoreEntry {
+                    gitignore: temp.path().join(".gitignore"),
+                    git_root: temp.path().to_path_buf(),
+                    entry: LOCAL_GITIGNORE_ENTRY.to_string(),
+                    tracked: Vec::new(),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let report = plan.execute(true);
+        assert!(report.dry_run && report.is_success());
+        assert_eq!(report.applied().count(), 2);
+        assert!(!temp.path().join("a").exists());
+        assert!(!temp.path().join(".gitignore").exists());
+    }
+
+    #[test]
+    fn test_execute_reports_skips_with_reasons() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join(".gitignore"), "target/\n/.kodegen\n").unwrap();
+        let plan = InitPlan {
+            actions: vec![
+                dir_action(temp.path()),
+                dir_action(&temp.path().join("new")),
+                InitAction::GitignoreEntry {
+                    gitignore: temp.path().join(".gitignore"),
+                    git_root: temp.path().to_path_buf(),
+                    entry: LOCAL_GITIGNORE_ENTRY.to_string(),
+                    tracked: Vec::new(),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let report = plan.execute(false);
+        assert!(report.is_success());
+        assert_eq!(
+            report.entries[0].outcome,
+            InitOutcome::Skipped { reason: "directory already exists".to_string() }
+        );
+        assert_eq!(report.entries[1].outcome, InitOutcome::Done);
+        assert!(matches!(&report.entries[2].outcome, InitOutcome::Skipped { reason } if reason.contains("already ignored")));
+        assert_eq!(fs::read_to_string(temp.path().join(".gitignore")).unwrap(), "target/\n/.kodegen\n");
+    }
+
+    #[test]
+    fn test_execution_stops_at_first_failure() {
+        let temp = tempfile::tempdir().unwrap();
+        let blocker = temp.path().join("file");
+        fs::write(&blocker, "not a directory").unwrap();
+        let plan = InitPlan {
+            actions: vec![dir_action(&blocker.join("sub")), dir_action(&temp.path().join("after"))],
+            ..Default::default()
+        };
+
+        let report = plan.execute(false);
+        assert!(matches!(report.entries[0].outcome, InitOutcome::Failed { .. }));
+        assert!(matches!(report.entries[1].outcome, InitOutcome::Skipped { .. }));
+        assert!(!temp.path().join("after").exists());
+        assert!(report.into_result().is_err());
+    }
+
+    #[test]
+    fn test_shared_layout_ignores_only_private_part() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir(temp.path().join(".git")).unwrap();
+        let gitignore = temp.path().join(".gitignore");
+        let action = InitAction::GitignoreEntry {
+            gitignore: gitignore.clone(),
+            git_root: temp.path().to_path_buf(),
+            entry: PRIVATE_GITIGNORE_ENTRY.to_string(),
+            tracked: SHARED_TRACKED_PATHS.iter().map(|p| p.to_string()).collect(),
+        };
+
+        action.apply().unwrap();
+        assert_eq!(fs::read_to_string(&gitignore).unwrap(), ".kodegen/local/\n");
+        assert!(is_ignored(temp.path(), &gitignore, ".kodegen/local/").unwrap());
+        assert!(!is_ignored(temp.path(), &gitignore, ".kodegen/toolset/").unwrap());
+        assert!(action.skip_reason().unwrap().is_some());
+
+        // A leftover rule ignoring all of .kodegen/ defeats the shared layout
+        fs::write(&gitignore, ".kodegen/\n").unwrap();
+        assert!(action.skip_reason().unwrap().is_none());
+        let err = action.apply().unwrap_err().to_string();
+        assert!(err.contains(".kodegen/toolset"), "{}", err);
+    }
+
+    #[test]
+    fn test_exclude_entry_added_and_removed() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        let exclude = crate::git::info_exclude_path(root).unwrap();
+
+        let action = InitAction::GitignoreEntry {
+            gitignore: exclude.clone(),
+            git_root: root.to_path_buf(),
+            entry: LOCAL_GITIGNORE_ENTRY.to_string(),
+            tracked: Vec::new(),
+        };
+        action.apply().unwrap();
+        assert_eq!(fs::read_to_string(&exclude).unwrap(), ".kodegen/\n");
+        assert_eq!(fs::read_to_string(root.join(".gitignore")).unwrap(), "target/\n");
+        assert!(action.skip_reason().unwrap().is_some());
+
+        let removal = InitPlan { actions: plan_ignore_rule_removal(root).unwrap(), ..Default::default() };
+        assert_eq!(
+            removal.actions,
+            vec![InitAction::RemoveGitignoreEntry {
+                gitignore: exclude.clone(),
+                git_root: root.to_path_buf(),
+                entry: LOCAL_GITIGNORE_ENTRY.to_string()
+            }]
+        );
+        assert!(removal.execute(true).is_success());
+        assert_eq!(fs::read_to_string(&exclude).unwrap(), ".kodegen/\n");
+        assert!(removal.execute(false).is_success());
+        assert_eq!(fs::read_to_string(&exclude).unwrap(), "");
+        assert!(plan_ignore_rule_removal(root).unwrap().is_empty());
+        assert!(!exclude.with_file_name(IGNORE_RULES_RECORD).exists());
+    }
+
+    #[test]
+    fn test_user_written_rules_are_not_removed() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".gitignore"), ".kodegen/\n").unwrap();
+
+        // Already ignored by the user's own line: init adds and records nothing
+        append_gitignore_entry(root, &root.join(".gitignore"), LOCAL_GITIGNORE_ENTRY).unwrap();
+        assert!(plan_ignore_rule_removal(root).unwrap().is_empty());
+        assert_eq!(remove_ignore_rules(root, false).unwrap(), Vec::<PathBuf>::new());
+        assert_eq!(fs::read_to_string(root.join(".gitignore")).unwrap(), ".kodegen/\n");
+    }
+
+    #[test]
+    fn test_repair_quarantines_blocking_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("data")).unwrap();
+        fs::write(root.join("data/memory"), "deleted dir replaced by a file").unwrap();
+        fs::write(root.join("toolset"), "oops").unwrap();
+
+        let mut plan = InitPlan { keep_going: true, ..Default::default() };
+        for path in [root.join("toolset"), root.join("data/stats"), root.join("data/memory")] {
+            plan.add_dir(root, path, true);
+        }
+        let quarantined: Vec<_> = plan
+            .actions
+            .iter()
+            .filter_map(|a| match a {
+                InitAction::Quarantine { path, .. } => Some(path.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(quarantined, vec![root.join("toolset"), root.join("data/memory")]);
+
+        let report = plan.execute(false);
+        assert!(report.is_success(), "{}", report);
+        assert!(root.join("toolset").is_dir());
+        assert!(root.join("data/memory").is_dir());
+
+        let quarantine = fs::read_dir(root.join(QUARANTINE_DIR)).unwrap().next().unwrap().unwrap().path();
+        assert_eq!(fs::read_to_string(quarantine.join("toolset")).unwrap(), "oops");
+    }
+
+    #[test]
+    fn test_back_to_back_repairs_use_separate_batches() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().join("kodegen");
+        fs::create_dir_all(&root).unwrap();
+
+        for contents in ["first", "second"] {
+            fs::write(root.join("toolset"), contents).unwrap();
+            let mut plan = InitPlan { keep_going: true, ..Default::default() };
+            plan.add_dir(&root, root.join("toolset"), true);
+            assert!(plan.execute(false).is_success());
+            fs::remove_dir(root.join("toolset")).unwrap();
+        }
+
+        let mut kept: Vec<String> = fs::read_dir(root.join(QUARANTINE_DIR))
+            .unwrap()
+            .map(|batch| fs::read_to_string(batch.unwrap().path().join("toolset")).unwrap())
+            .collect();
+        kept.sort();
+        assert_eq!(kept, ["first", "second"]);
+    }
+
+    #[test]
+    fn test_repair_reports_unfixable_root() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().join("kodegen");
+        fs::write(&root, "not a directory").unwrap();
+
+        let mut plan = InitPlan { keep_going: true, ..Default::default() };
+        plan.add_dir(&root, root.join("toolset"), true);
+        plan.add_dir(&root, root.join("claude"), true);
+        assert_eq!(plan.problems.len(), 1);
+
+        let report = plan.execute(true);
+        assert!(!report.is_success());
+        assert!(report.into_result().unwrap_err().to_string().contains("cannot fix"));
+    }
+
+    #[test]
+    fn test_gitignore_entry_appended_atomically() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir(temp.path().join(".git")).unwrap();
+        let gitignore = temp.path().join(".gitignore");
+        fs::write(&gitignore, "target/").unwrap();
+
+        append_gitignore_entry(temp.path(), &gitignore, LOCAL_GITIGNORE_ENTRY).unwrap();
+        append_gitignore_entry(temp.path(), &gitignore, LOCAL_GITIGNORE_ENTRY).unwrap();
+        assert_eq!(fs::read_to_string(&gitignore).unwrap(), "target/\n.kodegen/\n");
+    }
+
+    #[test]
+    fn test_crlf_line_endings_preserved() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir(temp.path().join(".git")).unwrap();
+        let gitignore = temp.path().join(".gitignore");
+        fs::write(&gitignore, "target/\r\n").unwrap();
+
+        append_gitignore_entry(temp.path(), &gitignore, LOCAL_GITIGNORE_ENTRY).unwrap();
+        assert_eq!(fs::read_to_string(&gitignore).unwrap(), "target/\r\n.kodegen/\r\n");
+        assert!(remove_gitignore_entry(&gitignore, LOCAL_GITIGNORE_ENTRY, false).unwrap());
+        assert_eq!(fs::read_to_string(&gitignore).unwrap(), "target/\r\n");
+    }
+
+    #[test]
+    fn test_ignore_file_errors_name_the_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let exclude = temp.path().join("exclude");
+        fs::create_dir(&exclude).unwrap();
+        let err = check_gitignore_file(&exclude).unwrap_err().to_string();
+        assert!(err.starts_with("exclude exists but is not a regular file"), "{}", err);
+    }
+}
@@ -0,0 +1,121 @@
+//! Named instances for running several kodegen installations side by side
+//!
+//! Setting `KODEGEN_INSTANCE=dev` gives that process tree its own runtime
+//! directories and ports, so a dev build doesn't collide with the stable one:
+//!
+//! | Resource                         | Default instance      | Instance `dev`                              |
+//! |----------------------------------|-----------------------|---------------------------------------------|
+//! | `state_dir()`, `log_dir()`, `cache_dir()`, `bin_dir()` | `{root}/state/`, ... | `{root}/instances/dev/state/`, ... |
+//! | Sockets in `XDG_RUNTIME_DIR`     | `kodegen/`            | `kodegen-dev/`                              |
+//! | Ports                            | [`CATEGORY_PORTS`](crate::CATEGORY_PORTS) | shifted by [`port_offset()`]   |
+//!
+//! Config files, toolsets, templates and `data_dir()` stay shared, so both
+//! instances see the same configuration. Set
+//! `KODEGEN_INSTANCE_ISOLATE_CONFIG=1` to give the instance its own user
+//! config layer (`{root}/instances/dev/`) as well.
+//!
+//! An unset or empty variable, or `default`, selects the default instance.
+
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::constants::{PORT_MAX, PORT_MIN};
+
+/// Environment variable selecting the instance
+pub const INSTANCE_ENV: &str = "KODEGEN_INSTANCE";
+
+/// Environment variable giving the instance its own user config layer
+pub const INSTANCE_ISOLATE_CONFIG_ENV: &str = "KODEGEN_INSTANCE_ISOLATE_CONFIG";
+
+/// Subdirectory of the user config root holding named instances
+pub const INSTANCES_DIR: &str = "instances";
+
+/// Name of the default instance
+const DEFAULT_INSTANCE: &str = "default";
+
+/// Spacing between instance port ranges
+const PORT_BLOCK: u16 = 100;
+
+/// Number of distinct port ranges available to named instances
+const PORT_BLOCKS: u16 = 64;
+
+// Every instance range must fit below 65536
+const _: () = assert!(PORT_MAX as u32 + (PORT_BLOCK as u32 * PORT_BLOCKS as u32) <= u16::MAX as u32);
+const _: () = assert!(PORT_MAX - PORT_MIN < PORT_BLOCK);
+
+/// Instance selected by `KODEGEN_INSTANCE` (`None` for the default instance)
+pub(crate) fn current() -> Result<Option<String>> {
+    parse(std::env::var_os(INSTANCE_ENV))
+}
+
+/// Parse and validate an instance name
+pub(crate) fn parse(value: Option<OsString>) -> Result<Option<String>> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let name = value
+        .into_string()
+        .map_err(|value| anyhow!("Invalid {}: {:?} is not valid UTF-8", INSTANCE_ENV, value))?;
+    let name = name.trim();
+    if name.is_empty() || name == DEFAULT_INSTANCE {
+        return Ok(None);
+    }
+    crate::validation::validate_name(name).map_err(|e| anyhow!("Invalid {}: {}", INSTANCE_ENV, e))?;
+    Ok(Some(name.to_string()))
+}
+
+/// Whether the instance also isolates the user config layer
+pub(crate) fn isolate_config() -> bool {
+    std::env::var(INSTANCE_ISOLATE_CONFIG_ENV).is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// Root of an instance's runtime directories
+pub(crate) fn dir(root: &Path, instance: Option<&str>) -> PathBuf {
+    match instance {
+        Some(name) => root.join(INSTANCES_DIR).join(name),
+        None => root.to_path_buf(),
+    }
+}
+
+/// Port shift of an instance relative to the default ranges
+///
+/// Named instances get one of 64 blocks of 100 ports above the defaults,
+/// derived from a stable hash of the name. Two names can land on the same
+/// block; set `KODEGEN_PORT_BASE` for one of them if that happens.
+pub(crate) fn port_offset(instance: Option<&str>) -> u16 {
+    let Some(name) = instance else {
+        return 0;
+    };
+    let digest = Sha256::digest(name.as_bytes());
+    let block = u16::from_be_bytes([digest[0], digest[1]]) % PORT_BLOCKS;
+    (block + 1) * PORT_BLOCK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_layout() {
+        assert_eq!(parse(None).unwrap(), None);
+        assert_eq!(parse(Some("".into())).unwrap(), None);
+        assert_eq!(parse(Some("default".into())).unwrap(), None);
+        assert_eq!(parse(Some(" dev ".into())).unwrap(), Some("dev".to_string()));
+        assert!(parse(Some("../stable".into())).is_err());
+
+        let root = Path::new("/home/user/.config/kodegen");
+        assert_eq!(dir(root, None), root);
+        assert_eq!(dir(root, Some("dev")), root.join("instances/dev"));
+    }
+
+    #[test]
+    fn test_port_offset() {
+        assert_eq!(port_offset(None), 0);
+        let dev = port_offset(Some("dev"));
+        assert_eq!(dev, port_offset(Some("dev")), "must be stable");
+        assert!(dev >= PORT_BLOCK && dev.is_multiple_of(PORT_BLOCK) && dev <= PORT_BLOCK * PORT_BLOCKS);
+        assert!(crate::PortMap::with_base(PORT_MIN + PORT_BLOCK * PORT_BLOCKS).is_ok());
+    }
+}
@@ -0,0 +1,417 @@
+//! Migration of legacy user config locations
+//!
+//! Older builds (and platform logic changes) left user data in locations that
+//! [`user_config_dir()`](crate::KodegenConfig::user_config_dir) no longer
+//! resolves to:
+//!
+//! - `~/.kodegen` (pre-XDG layout), unless `$HOME` is itself a git
+//!   repository (dotfiles setups), where it is the git-local config layer
+//! - `~/.config/kodegen` (XDG fallback previously used on macOS/Windows, or
+//!   the default XDG path when `XDG_CONFIG_HOME` now points elsewhere)
+//! - the platform config dir (e.g. `~/Library/Application Support/kodegen`)
+//!   when it differs from the current root
+//!
+//! Files are moved into the current root. Identical files are merged
+//! (the legacy copy is removed); files that differ are left in place and
+//! reported as conflicts. A marker file in the current root records that
+//! migration happened so it only runs once.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Marker file (in the current user config root) recording a completed migration
+pub const LEGACY_MIGRATION_MARKER: &str = ".legacy-migrated.json";
+
+/// Options for [`migrate_legacy_locations()`](crate::KodegenConfig::migrate_legacy_locations)
+#[derive(Debug, Clone, Default)]
+pub struct LegacyMigrationOptions {
+    /// Report what would be moved without touching the filesystem
+    pub dry_run: bool,
+    /// Run even if the marker file says migration already happened
+    pub force: bool,
+}
+
+/// A legacy file that could not be merged because the current root has a different file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LegacyConflict {
+    /// File in the legacy location (left in place)
+    pub source: PathBuf,
+    /// Existing file in the current root (kept)
+    pub destination: PathBuf,
+}
+
+/// Outcome of a legacy location migration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LegacyMigrationReport {
+    /// Current user config root that files were moved into
+    pub destination: PathBuf,
+    /// Legacy roots that were found
+    pub sources: Vec<PathBuf>,
+    /// Files (or whole roots) moved, as `(from, to)`
+    pub moved: Vec<(PathBuf, PathBuf)>,
+    /// Legacy files removed because an identical file already existed
+    pub merged: Vec<PathBuf>,
+    /// Files that differ between legacy and current root
+    pub conflicts: Vec<LegacyConflict>,
+    /// Marker file written (not set for dry-runs)
+    pub marker: Option<PathBuf>,
+    /// Migration was skipped because the marker file already exists
+    pub already_migrated: bool,
+    /// Whether this was a dry-run
+    pub dry_run: bool,
+}
+
+/// Contents of the marker file
+#[derive(Debug, Serialize, Deserialize)]
+struct Marker {
+    migrated_at: chrono::DateTime<chrono::Utc>,
+    sources: Vec<PathBuf>,
+    moved: usize,
+    merged: usize,
+    conflicts: Vec<LegacyConflict>,
+}
+
+/// Candidate legacy roots for the current platform (existing or not)
+pub(crate) fn candidate_roots() -> Vec<PathBuf> {
+    candidate_roots_in(dirs::home_dir().as_deref(), dirs::config_dir().as_deref())
+}
+
+fn candidate_roots_in(home: Option<&Path>, config: Option<&Path>) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(home) = home {
+        // In a git repository at $HOME, ~/.kodegen is that repository's local layer
+        if fs::symlink_metadata(home.join(".git")).is_err() {
+            candidates.push(home.join(".kodegen"));
+        }
+        candidates.push(home.join(".config").join("kodegen"));
+    }
+    if let Some(config) = config {
+        candidates.push(config.join("kodegen"));
+    }
+    candidates
+}
+
+/// Legacy roots that exist and are distinct from `current`
+pub(crate) fn detect(current: &Path, candidates: &[PathBuf]) -> Vec<PathBuf> {
+    let current_canonical = current.canonicalize().unwrap_or_else(|_| current.to_path_buf());
+    let mut found: Vec<PathBuf> = Vec::new();
+
+    for candidate in candidates {
+        let Ok(metadata) = fs::symlink_metadata(candidate) else {
+            continue;
+        };
+        // A symlinked legacy root usually points at the current root; never follow it
+        if !metadata.is_dir() {
+            continue;
+        }
+        let canonical = candidate.canonicalize().unwrap_or_else(|_| candidate.clone());
+        if canonical == current_canonical
+            || current_canonical.starts_with(&canonical)
+            || found.iter().any(|f| f.canonicalize().ok().as_ref() == Some(&canonical))
+        {
+            continue;
+        }
+        found.push(candidate.clone());
+    }
+    found
+}
+
+/// Migrate the default legacy roots into the current user config root
+pub(crate) fn migrate(options: &LegacyMigrationOptions) -> Result<LegacyMigrationReport> {
+    let current = crate::KodegenConfig::user_config_dir()?;
+    migrate_roots(&current, &candidate_roots(), options)
+}
+
+/// Migrate `candidates` into `current`
+pub(crate) fn migrate_roots(
+    current: &Path,
+    candidates: &[PathBuf],
+    options: &LegacyMigrationOptions,
+) -> Result<LegacyMigrationReport> {
+    let mut report = LegacyMigrationReport {
+        destination: current.to_path_buf(),
+        dry_run: options.dry_run,
+        ..Default::default()
+    };
+
+    let marker_path = current.join(LEGACY_MIGRATION_MARKER);
+    if marker_path.exists() && !options.force {
+        report.already_migrated = true;
+        return Ok(report);
+    }
+
+    report.sources = detect(current, candidates);
+    if report.sources.is_empty() {
+        return Ok(report);
+    }
+
+    let mut merge = Merge { current, dry_run: options.dry_run, report: &mut report };
+    for source in merge.report.sources.clone() {
+        if merge.existing(current).is_none() {
+            // Fast path: nothing to merge with, move the whole root
+            if !options.dry_run {
+                if let Some(parent) = current.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                move_path(&source, current)?;
+            }
+            merge.report.moved.push((source, current.to_path_buf()));
+            continue;
+        }
+        merge.merge_dir(&source, &source)?;
+        if !options.dry_run {
+            remove_empty_dirs(&source);
+        }
+    }
+
+    for conflict in &report.conflicts {
+        log::warn!(
+            "Legacy config conflict: {} differs from {} (legacy file left in place)",
+            conflict.source.display(),
+            conflict.destination.display()
+        );
+    }
+
+    if !options.dry_run {
+        let marker = Marker {
+            migrated_at: chrono::Utc::now(),
+            sources: report.sources.clone(),
+            moved: report.moved.len(),
+            merged: report.merged.len(),
+            conflicts: report.conflicts.clone(),
+        };
+        fs::create_dir_all(current)?;
+        let mut temp_file = NamedTempFile::new_in(current)?;
+        temp_file.write_all(serde_json::to_string_pretty(&marker)?.as_bytes())?;
+        temp_file.as_file().sync_all()?;
+        temp_file
+            .persist(&marker_path)
+            .with_context(|| format!("Failed to write migration marker: {}", marker_path.display()))?;
+        report.marker = Some(marker_path);
+        log::info!(
+            "Migrated legacy config from {} into {}",
+            report.sources.iter().map(|s| s.display().to_string()).collect::<Vec<_>>().join(", "),
+            current.display()
+        );
+    }
+
+    Ok(report)
+}
+
+/// Merge state shared across legacy roots
+struct Merge<'a> {
+    current: &'a Path,
+    dry_run: bool,
+    report: &'a mut LegacyMigrationReport,
+}
+
+impl Merge<'_> {
+    /// Path holding what `destination` contains once earlier moves are done
+    ///
+    /// A dry-run moves nothing, so a destination filled by an earlier source
+    /// is looked up in that source instead. Later sources are then reported
+    /// as merged or conflicting, as they would be for a real run.
+    fn existing(&self, destination: &Path) -> Option<PathBuf> {
+        if fs::symlink_metadata(destination).is_ok() {
+            return Some(destination.to_path_buf());
+        }
+        self.report.moved.iter().rev().find_map(|(from, to)| {
+            let planned = from.join(destination.strip_prefix(to).ok()?);
+            fs::symlink_metadata(&planned).is_ok().then_some(planned)
+        })
+    }
+
+    /// Recursively merge `dir` (inside legacy `root`) into the current root
+    fn merge_dir(&mut self, dir: &Path, root: &Path) -> Result<()> {
+        let entries = fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+        for entry in entries {
+            let source = entry?.path();
+            let relative = source.strip_prefix(root).unwrap_or(&source);
+            let destination = self.current.join(relative);
+            let source_type = fs::symlink_metadata(&source)?.file_type();
+
+            let Some(existing) = self.existing(&destination) else {
+                if !self.dry_run {
+                    if let Some(parent) = destination.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    move_path(&source, &destination)?;
+                }
+                self.report.moved.push((source, destination));
+                continue;
+            };
+            let existing_type = fs::symlink_metadata(&existing)?.file_type();
+
+            if source_type.is_dir() && existing_type.is_dir() {
+                self.merge_dir(&source, root)?;
+            } else if source_type.is_file() && existing_type.is_file() && same_contents(&source, &existing) {
+                if !self.dry_run {
+                    fs::remove_file(&source)?;
+                }
+                self.report.merged.push(source);
+            } else {
+                self.report.conflicts.push(LegacyConflict { source, destination });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn same_contents(a: &Path, b: &Path) -> bool {
+    match (fs::read(a), fs::read(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Move a file or directory, falling back to copy + delete across filesystems
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_recursive(from, to)
+        .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))?;
+    if fs::symlink_metadata(from)?.is_dir() {
+        fs::remove_dir_all(from)?;
+    } else {
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> Result<()> {
+    let metadata = fs::symlink_metadata(from)?;
+    if metadata.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else if metadata.file_type().is_symlink() {
+        let target = fs::read_link(from)?;
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(target, to)?;
+        #[cfg(windows)]
+        std::os::windows::fs::symlink_file(target, to)?;
+    } else {
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+/// Remove empty directories bottom-up (including `dir` itself); errors are ignored
+fn remove_empty_dirs(dir: &Path) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                remove_empty_dirs(&entry.path());
+            }
+        }
+    }
+    let _ = fs::remove_dir(dir);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_moves_whole_root_when_current_missing() {
+        let temp = tempfile::tempdir().unwrap();
+        let legacy = temp.path().join(".kodegen");
+        let current = temp.path().join("config/kodegen");
+        write(&legacy.join("toolset/core.json"), "{}");
+
+        let report = migrate_roots(&current, std::slice::from_ref(&legacy), &Default::default()).unwrap();
+        assert_eq!(report.sources, vec![legacy.clone()]);
+        assert!(current.join("toolset/core.json").exists());
+        assert!(!legacy.exists());
+        assert!(current.join(LEGACY_MIGRATION_MARKER).exists());
+    }
+
+    #[test]
+    fn test_merge_with_conflicts_and_marker() {
+        let temp = tempfile::tempdir().unwrap();
+        let legacy = temp.path().join(".kodegen");
+        let current = temp.path().join("kodegen");
+        write(&legacy.join("toolset/new.json"), "{}");
+        write(&legacy.join("toolset/same.json"), "same");
+        write(&legacy.join("config.json"), "old");
+        write(&current.join("toolset/same.json"), "same");
+        write(&current.join("config.json"), "new");
+
+        // Dry-run changes nothing
+        let dry = LegacyMigrationOptions { dry_run: true, force: false };
+        let report = migrate_roots(&current, std::slice::from_ref(&legacy), &dry).unwrap();
+        assert_eq!(report.moved.len(), 1);
+        assert_eq!(report.merged.len(), 1);
+        assert_eq!(report.conflicts.len(), 1);
+        assert!(legacy.join("toolset/new.json").exists());
+        assert!(!current.join(LEGACY_MIGRATION_MARKER).exists());
+
+        let report = migrate_roots(&current, std::slice::from_ref(&legacy), &Default::default()).unwrap();
+        assert_eq!(report.conflicts[0].destination, current.join("config.json"));
+        assert!(current.join("toolset/new.json").exists());
+        assert_eq!(fs::read_to_string(current.join("config.json")).unwrap(), "new");
+        assert!(legacy.join("config.json").exists(), "conflicting legacy file must be kept");
+        assert!(!legacy.join("toolset").exists(), "emptied legacy dirs are removed");
+
+        // Marker prevents a second run
+        let report = migrate_roots(&current, std::slice::from_ref(&legacy), &Default::default()).unwrap();
+        assert!(report.already_migrated);
+    }
+
+    #[test]
+    fn test_dry_run_reports_merges_between_sources() {
+        let temp = tempfile::tempdir().unwrap();
+        let first = temp.path().join(".kodegen");
+        let second = temp.path().join(".config/kodegen");
+        let current = temp.path().join("kodegen");
+        write(&first.join("toolset/core.json"), "first");
+        write(&first.join("config.json"), "same");
+        write(&second.join("toolset/core.json"), "second");
+        write(&second.join("config.json"), "same");
+        write(&second.join("toolset/extra.json"), "{}");
+        let sources = [first.clone(), second.clone()];
+
+        let dry = LegacyMigrationOptions { dry_run: true, force: false };
+        let planned = migrate_roots(&current, &sources, &dry).unwrap();
+        let applied = migrate_roots(&current, &sources, &Default::default()).unwrap();
+
+        assert_eq!(planned.moved, applied.moved);
+        assert_eq!(planned.merged, applied.merged);
+        assert_eq!(planned.conflicts, applied.conflicts);
+        assert_eq!(applied.moved, [(first, current.clone()), (second.join("toolset/extra.json"), current.join("toolset/extra.json"))]);
+        assert_eq!(applied.merged, [second.join("config.json")]);
+        assert_eq!(applied.conflicts[0].source, second.join("toolset/core.json"));
+    }
+
+    #[test]
+    fn test_home_dotdir_skipped_when_home_is_a_repo() {
+        let temp = tempfile::tempdir().unwrap();
+        let home = temp.path();
+        assert!(candidate_roots_in(Some(home), None).contains(&home.join(".kodegen")));
+
+        fs::create_dir(home.join(".git")).unwrap();
+        let candidates = candidate_roots_in(Some(home), None);
+        assert!(!candidates.contains(&home.join(".kodegen")));
+        assert!(candidates.contains(&home.join(".config/kodegen")));
+    }
+
+    #[test]
+    fn test_current_root_is_not_a_legacy_root() {
+        let temp = tempfile::tempdir().unwrap();
+        let current = temp.path().join("kodegen");
+        fs::create_dir_all(&current).unwrap();
+        assert!(detect(&current, std::slice::from_ref(&current)).is_empty());
+    }
+}
@@ -17,8 +17,13 @@
 //! **Root directories:**
 //! - [`user_config_dir()`](KodegenConfig::user_config_dir) - User-global root directory (~/.config/kodegen)
 //! - [`local_config_dir()`](KodegenConfig::local_config_dir) - Git workspace-local config directory (.kodegen/)
+//! - [`local_private_dir()`](KodegenConfig::local_private_dir) - Private, never committed part (.kodegen/local/)
 //!
-//! **Subdirectories (all under root):**
+//! **Instances:**
+//! - [`instance()`](KodegenConfig::instance) - Instance selected by `KODEGEN_INSTANCE` (e.g. a dev build next to stable)
+//! - [`instance_dir()`](KodegenConfig::instance_dir) - Root of the instance's state, logs, cache and bin (root/instances/{name}/)
+//!
+//! **Subdirectories (all under root; state, logs, cache and bin under the instance root):**
 //! - [`config_dir()`](KodegenConfig::config_dir) - Configuration files (root/config/)
 //! - [`toolset_dir()`](KodegenConfig::toolset_dir) - Tool definitions (root/toolset/)
 //! - [`state_dir()`](KodegenConfig::state_dir) - Runtime state: PIDs, sockets (root/state/)
@@ -27,9 +32,56 @@
 //! - [`cache_dir()`](KodegenConfig::cache_dir) - Temporary cache: builds, downloads (root/cache/)
 //! - [`bin_dir()`](KodegenConfig::bin_dir) - Binaries (root/bin/)
 //!
+//! **Runtime state:**
+//! - [`state_registry()`](KodegenConfig::state_registry) - PID/socket records per category with stale detection
+//!
+//! **Ports:**
+//! - [`port_map()`](KodegenConfig::port_map) - Service ports with `ports.json` / `KODEGEN_PORT_*` overrides and conflict checks
+//! - [`service_socket_path()`](KodegenConfig::service_socket_path) - Unix socket per category ($XDG_RUNTIME_DIR/kodegen/ or root/state/sockets/)
+//! - [`publish_service_endpoint()`](KodegenConfig::publish_service_endpoint) - Publish a dynamically bound [`Endpoint`] (root/state/{category}/server.json)
+//! - [`resolve_service_endpoint()`](KodegenConfig::resolve_service_endpoint) - Published endpoint, else [`PortMap`] / [`CATEGORY_PORTS`]
+//!
+//! **Infrastructure headers:**
+//! - [`InfraContext`] - Build and parse the `X_KODEGEN_*` headers
+//! - [`HeaderSigner`] - HMAC-sign and verify them with the per-user key (root/state/infra.key)
+//!
+//! **Logs:**
+//! - [`log_file()`](KodegenConfig::log_file) - Per-service log file (root/logs/{category}/{service}.log)
+//! - [`log_writer()`](KodegenConfig::log_writer) - Rotating writer with [`LogRetention`]; see also [`FileLogger`]
+//! - [`prune_logs()`](KodegenConfig::prune_logs) - Delete rotated logs by age and total size
+//!
+//! **Cache management:**
+//! - [`cache_namespace_dir()`](KodegenConfig::cache_namespace_dir) - Per-namespace cache subdirectory (root/cache/{namespace}/)
+//! - [`cache_gc()`](KodegenConfig::cache_gc) - Size accounting and age/LRU eviction with a [`CachePolicy`]
+//!
+//! **Legacy locations:**
+//! - [`migrate_legacy_locations()`](KodegenConfig::migrate_legacy_locations) - Move `~/.kodegen` and old XDG roots into the current root
+//!
+//! **Initialization:**
+//! - [`init_structure()`](KodegenConfig::init_structure) - Create local and user directory structures
+//! - [`init_structure_with()`](KodegenConfig::init_structure_with) - Same, with [`InitOptions`] (dry-run, scope) and an [`InitReport`]
+//! - [`repair_structure()`](KodegenConfig::repair_structure) - Check the full layout and fix or report problems
+//! - [`list_templates()`](KodegenConfig::list_templates) - Project templates for scaffolding `.kodegen/`
+//! - [`remove_ignore_rules()`](KodegenConfig::remove_ignore_rules) - Remove the `.kodegen/` ignore rules init added
+//! - [`teardown()`](KodegenConfig::teardown) - Reverse initialization ([`TeardownOptions`], dry-run by default)
+//!
 //! **File resolution:**
 //! - [`resolve_toolset()`](KodegenConfig::resolve_toolset) - Resolve toolset file with precedence
 //! - [`resolve_config_file()`](KodegenConfig::resolve_config_file) - Resolve config file with precedence
+//! - [`load_toolset()`](KodegenConfig::load_toolset) - Resolve and parse a toolset file (deprecated tool names are rewritten via [`canonical_tool_name()`])
+//! - [`Toolset::routes()`] - Categories and servers (with endpoints) a toolset needs
+//!
+//! **Config loading:**
+//! - [`load_config()`](KodegenConfig::load_config) - Load and deserialize a config file, interpolating `${env:NAME}`, `${git_root}`, `${secret:NAME}`, ...
+//! - [`secrets()`](KodegenConfig::secrets) - Encrypted secrets store (root/data/secrets/)
+//! - [`migrate_config()`](KodegenConfig::migrate_config) - Migrate a config file to the current `version` (with dry-run diff)
+//!
+//! **Manifest:**
+//! - [`registry_manifest()`] - Categories, tools, aliases, default ports and header names as versioned JSON
+//!
+//! **Schemas:**
+//! - [`toolset_schema()`] / [`register_config_schema()`] - JSON Schemas written to root/schemas/ by `init_structure()`
+//! - [`validate_toolset_file()`] / [`validate_config_file()`] - Validation with file, line and column
 //!
 //! This uniform `Result` pattern provides:
 //! 1. **Consistency** - All similar operations use the same error handling pattern
@@ -105,10 +157,57 @@ mod init;
 pub(crate) mod platform;  // Keep for user_config_dir implementation
 mod toolset;
 mod path_display;
+mod secrets;
+mod interpolate;
+mod loader;
+mod schema;
+mod versioning;
+mod legacy;
+mod templates;
+mod teardown;
+mod cache;
+mod logs;
+mod state;
+mod headers;
+mod signing;
+mod ports;
+mod services;
+mod instance;
+mod manifest;
 
 pub mod constants;
 
 pub use path_display::shorten_path_for_display;
+pub use validation::{validate_name, verify_within_directory};
+pub use secrets::SecretStore;
+pub use loader::LoadOptions;
+pub use init::{
+    IgnoreFile, InitAction, InitOptions, InitOutcome, InitPlan, InitProblem, InitReport, InitReportEntry, QUARANTINE_DIR,
+};
+pub use toolset::{Toolset, ToolsetRoutes};
+pub use templates::{BUILTIN_TEMPLATES, TEMPLATES_DIR};
+pub use teardown::{TeardownOptions, TeardownScope};
+pub use state::{StateRecord, StateRegistry};
+pub use headers::InfraContext;
+pub use signing::{HeaderSigner, INFRA_KEY_FILE};
+pub use ports::{PortConfig, PortMap, Transport, PORTS_CONFIG_FILE, PORT_BASE_ENV, PORT_ENV_PREFIX};
+pub use services::{Endpoint, SERVICE_RECORD_NAME, SOCKETS_DIR};
+pub use instance::{INSTANCES_DIR, INSTANCE_ENV, INSTANCE_ISOLATE_CONFIG_ENV};
+pub use manifest::{registry_manifest, MANIFEST_VERSION};
+pub use logs::{FileLogger, LogPruneReport, LogRetention, RotatingLogWriter};
+pub use cache::{
+    CacheEntry, CachePolicy, CacheReport, EvictionReason, NamespaceUsage, DEFAULT_CACHE_MAX_AGE, DEFAULT_CACHE_QUOTA,
+};
+pub use legacy::{
+    LegacyConflict, LegacyMigrationOptions, LegacyMigrationReport, LEGACY_MIGRATION_MARKER,
+};
+pub use versioning::{
+    current_version, register_migration, MigrateOptions, MigrationFn, MigrationReport, VERSION_FIELD,
+};
+pub use schema::{
+    config_schema, register_config_schema, toolset_schema, validate_config_file,
+    validate_toolset_file, SchemaError,
+};
 
 // Re-export all constants for convenience
 pub use constants::*;
@@ -118,7 +217,9 @@ pub use constants::*;
 // ============================================================================
 // These headers pass infrastructure context from kodegen stdio server to HTTP
 // backend servers. Used for CWD tracking, git root detection, and connection-
-// scoped resource isolation.
+// scoped resource isolation. Build and parse them with `InfraContext` rather
+// than by hand: it handles path encoding and validation. Backends that must not
+// trust any local process sign and verify them with `HeaderSigner`.
 
 /// Header containing the connection ID for this stdio connection instance.
 /// Used by backend servers for connection-scoped resource isolation (terminals, browsers, etc.)
@@ -132,6 +233,18 @@ pub const X_KODEGEN_PWD: &str = "x-kodegen-pwd";
 /// Used for repository-aware operations and path resolution.
 pub const X_KODEGEN_GITROOT: &str = "x-kodegen-gitroot";
 
+/// Header containing the Unix time (seconds) at which the headers were signed.
+/// Requests outside the verifier's allowed clock skew are rejected.
+pub const X_KODEGEN_TIMESTAMP: &str = "x-kodegen-timestamp";
+
+/// Header containing a random single-use value (hex).
+/// Verifiers reject a nonce seen before within the skew window (replay).
+pub const X_KODEGEN_NONCE: &str = "x-kodegen-nonce";
+
+/// Header containing the HMAC-SHA256 signature (hex) over the infrastructure headers.
+/// Keyed with the per-user secret in `state_dir()/infra.key`.
+pub const X_KODEGEN_SIGNATURE: &str = "x-kodegen-signature";
+
 /// Try to resolve a file within a directory with TOCTOU-resistant canonicalization
 ///
 /// This function eliminates the TOCTOU race condition by avoiding explicit `.exists()`
@@ -203,6 +316,43 @@ pub(crate) fn try_resolve_in_dir(base_dir: &Path, subdir: &str, filename: &str)
     }
 }
 
+/// Find `filename` in the first of `layers` that has it
+///
+/// Unlike [`try_resolve_in_dir()`], a file that exists but cannot be used
+/// (inaccessible, a path through a non-directory, a symlink leaving its
+/// layer) is an error rather than absent, so only a missing file yields `None`.
+pub(crate) fn find_in_layers(layers: &[PathBuf], filename: &str) -> Result<Option<PathBuf>> {
+    for layer in layers {
+        if let Some(path) = try_resolve_in_dir(layer, "", filename) {
+            return Ok(Some(path));
+        }
+        let candidate = layer.join(filename);
+        match std::fs::metadata(&candidate) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(anyhow::Error::new(e).context(format!("Failed to access config file: {}", candidate.display())));
+            }
+            Ok(_) => anyhow::bail!("Config file resolves outside its directory: {}", candidate.display()),
+        }
+    }
+    Ok(None)
+}
+
+/// Private subdirectory of `.kodegen/` for personal overrides
+///
+/// With the shared layout ([`InitOptions::shared`]) `.kodegen/` is committed and
+/// only `.kodegen/local/` is ignored. Files here take precedence over `.kodegen/`.
+pub const LOCAL_PRIVATE_DIR: &str = "local";
+
+/// Git-local config layers in precedence order: `.kodegen/local/`, then `.kodegen/`
+///
+/// Empty if not in a git repository.
+pub(crate) fn local_layers() -> Vec<PathBuf> {
+    KodegenConfig::local_config_dir()
+        .map(|dir| vec![dir.join(LOCAL_PRIVATE_DIR), dir])
+        .unwrap_or_default()
+}
+
 /// Main configuration path resolver
 pub struct KodegenConfig;
 
@@ -235,40 +385,79 @@ impl KodegenConfig {
         git::find_git_root().map(|root| root.join(".kodegen"))
     }
 
+    /// Get the private part of the git-local config directory
+    ///
+    /// **Returns**: `${git_root}/.kodegen/local`
+    ///
+    /// Holds personal overrides that are never committed; see [`LOCAL_PRIVATE_DIR`].
+    pub fn local_private_dir() -> Result<PathBuf> {
+        Ok(Self::local_config_dir()?.join(LOCAL_PRIVATE_DIR))
+    }
+
+    /// Get the instance selected by `KODEGEN_INSTANCE`
+    ///
+    /// **Returns**: `None` for the default instance (variable unset, empty or `default`)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the instance name is not a valid name.
+    pub fn instance() -> Result<Option<String>> {
+        instance::current()
+    }
+
+    /// Get the root of the current instance's runtime directories
+    ///
+    /// **Returns**: `{root}` for the default instance, `{root}/instances/{name}/` otherwise
+    ///
+    /// `state_dir()`, `log_dir()`, `cache_dir()` and `bin_dir()` live here.
+    pub fn instance_dir() -> Result<PathBuf> {
+        Ok(instance::dir(&Self::user_config_dir()?, Self::instance()?.as_deref()))
+    }
+
+    /// User config layer: the shared root, or the instance root when
+    /// `KODEGEN_INSTANCE_ISOLATE_CONFIG` is set
+    pub(crate) fn user_layer_dir() -> Result<PathBuf> {
+        if instance::isolate_config() {
+            Self::instance_dir()
+        } else {
+            Self::user_config_dir()
+        }
+    }
+
     /// Get config subdirectory (for daemon configuration files)
     ///
-    /// **Returns**: `{root}/config/`
+    /// **Returns**: `{root}/config/` (shared by all instances unless isolated)
     ///
     /// Example: `~/.config/kodegen/config/`
     pub fn config_dir() -> Result<PathBuf> {
-        Ok(Self::user_config_dir()?.join("config"))
+        Ok(Self::user_layer_dir()?.join("config"))
     }
 
     /// Get toolset subdirectory (for tool definitions)
     ///
-    /// **Returns**: `{root}/toolset/`
+    /// **Returns**: `{root}/toolset/` (shared by all instances unless isolated)
     ///
     /// Example: `~/.config/kodegen/toolset/`
     pub fn toolset_dir() -> Result<PathBuf> {
-        Ok(Self::user_config_dir()?.join("toolset"))
+        Ok(Self::user_layer_dir()?.join("toolset"))
     }
 
     /// Get state subdirectory (for PIDs, sockets, runtime state)
     ///
-    /// **Returns**: `{root}/state/`
+    /// **Returns**: `{instance root}/state/`
     ///
-    /// Example: `~/.config/kodegen/state/`
+    /// Example: `~/.config/kodegen/state/`, or `~/.config/kodegen/instances/dev/state/`
     pub fn state_dir() -> Result<PathBuf> {
-        Ok(Self::user_config_dir()?.join("state"))
+        Ok(Self::instance_dir()?.join("state"))
     }
 
     /// Get log subdirectory (for .log files)
     ///
-    /// **Returns**: `{root}/logs/`
+    /// **Returns**: `{instance root}/logs/`
     ///
     /// Example: `~/.config/kodegen/logs/`
     pub fn log_dir() -> Result<PathBuf> {
-        Ok(Self::user_config_dir()?.join("logs"))
+        Ok(Self::instance_dir()?.join("logs"))
     }
 
     /// Get data subdirectory (for databases, stats, caches, certificates)
@@ -282,20 +471,157 @@ impl KodegenConfig {
 
     /// Get bin subdirectory (for binary storage before symlinking)
     ///
-    /// **Returns**: `{root}/bin/`
+    /// **Returns**: `{instance root}/bin/`
     ///
     /// Example: `~/.config/kodegen/bin/`
     pub fn bin_dir() -> Result<PathBuf> {
-        Ok(Self::user_config_dir()?.join("bin"))
+        Ok(Self::instance_dir()?.join("bin"))
     }
 
     /// Get cache subdirectory (for temporary build artifacts, downloads, Chrome cache)
     ///
-    /// **Returns**: `{root}/cache/`
+    /// **Returns**: `{instance root}/cache/`
     ///
     /// Example: `~/.config/kodegen/cache/`
     pub fn cache_dir() -> Result<PathBuf> {
-        Ok(Self::user_config_dir()?.join("cache"))
+        Ok(Self::instance_dir()?.join("cache"))
+    }
+
+    /// Open the runtime state registry in `state_dir()`
+    ///
+    /// See [`StateRegistry`] for the record format and stale-entry handling.
+    pub fn state_registry() -> Result<StateRegistry> {
+        StateRegistry::open()
+    }
+
+    /// Get the service port assignments
+    ///
+    /// Starts from [`CATEGORY_PORTS`] and applies overrides from
+    /// `ports.json` (local > user precedence), `KODEGEN_PORT_BASE` and
+    /// `KODEGEN_PORT_{CATEGORY}`. See [`PortMap`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an override is invalid or two services would share a port.
+    pub fn port_map() -> Result<PortMap> {
+        PortMap::load()
+    }
+
+    /// Get the Unix socket path for a category's server
+    ///
+    /// **Returns**: `$XDG_RUNTIME_DIR/kodegen/{category}.sock` when
+    /// `XDG_RUNTIME_DIR` is set, else `{root}/state/sockets/{category}.sock`;
+    /// paths too long for `sun_path` move to a short directory in the temp dir.
+    ///
+    /// The directory is created with mode 0700.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the category is unknown or the directory is owned
+    /// by another user or accessible to others.
+    pub fn service_socket_path(category: &Category) -> Result<PathBuf> {
+        let path = services::socket_path(&Self::socket_dir()?, category)?;
+        services::prepare_socket_dir(&path)?;
+        Ok(path)
+    }
+
+    /// Publish the endpoint the current process serves `category` on
+    ///
+    /// Lets a server bind an ephemeral port (port 0) or a socket and still be
+    /// found by [`resolve_service_endpoint()`](Self::resolve_service_endpoint).
+    /// The record is owned by the current process and ignored once it exits.
+    ///
+    /// **Returns**: path of the record, `{root}/state/{category}/server.json`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if another live process already serves the category.
+    pub fn publish_service_endpoint(category: &Category, endpoint: &Endpoint, version: &str) -> Result<PathBuf> {
+        services::publish(&Self::state_registry()?, category, endpoint, version)
+    }
+
+    /// Remove the current process's published endpoint for `category`
+    ///
+    /// Returns whether a record was removed.
+    pub fn unpublish_service_endpoint(category: &Category) -> Result<bool> {
+        services::unpublish(&Self::state_registry()?, category)
+    }
+
+    /// Resolve the endpoint serving `category`
+    ///
+    /// Uses the endpoint published by a running server if there is one, then
+    /// the [`port_map()`](Self::port_map) selection: the category's
+    /// [`service_socket_path()`](Self::service_socket_path) if its transport
+    /// is `unix`, else its port, which defaults to [`CATEGORY_PORTS`].
+    /// Memory tools resolve to the candle-agent server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the category is unknown or the port overrides are invalid.
+    pub fn resolve_service_endpoint(category: &Category) -> Result<Endpoint> {
+        services::resolve(&Self::state_registry()?, &Self::port_map()?, &Self::socket_dir()?, category)
+    }
+
+    /// Preferred directory for service sockets
+    fn socket_dir() -> Result<PathBuf> {
+        let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+        Ok(services::socket_dir(&Self::state_dir()?, runtime_dir, Self::instance()?.as_deref()))
+    }
+
+    /// Get the active log file for a service
+    ///
+    /// **Returns**: `{root}/logs/{category}/{service}.log`
+    ///
+    /// Example: `log_file("browser", "chromium")` -> `~/.config/kodegen/logs/browser/chromium.log`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `category` or `service` is not a valid name.
+    pub fn log_file(category: &str, service: &str) -> Result<PathBuf> {
+        logs::log_file(&Self::log_dir()?, category, service)
+    }
+
+    /// Open a rotating writer for a service's log file
+    ///
+    /// The writer rotates, compresses and prunes according to `retention`;
+    /// pass it to a logger accepting a writer, or wrap it in a [`FileLogger`].
+    pub fn log_writer(category: &str, service: &str, retention: LogRetention) -> Result<RotatingLogWriter> {
+        RotatingLogWriter::open(Self::log_file(category, service)?, Self::log_dir()?, retention)
+    }
+
+    /// Delete rotated log files beyond the retention limits
+    ///
+    /// Active `{service}.log` files are never deleted.
+    pub fn prune_logs(retention: &LogRetention) -> Result<LogPruneReport> {
+        logs::prune(&Self::log_dir()?, retention, std::time::SystemTime::now())
+    }
+
+    /// Get (and create) a namespaced cache subdirectory
+    ///
+    /// **Returns**: `{root}/cache/{namespace}/`
+    ///
+    /// Example: `cache_namespace_dir("chrome")` -> `~/.config/kodegen/cache/chrome/`
+    ///
+    /// Each top-level file or directory inside a namespace is accounted and
+    /// evicted as one unit by [`cache_gc()`](Self::cache_gc).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the namespace is not a valid name or the directory
+    /// cannot be created.
+    pub fn cache_namespace_dir(namespace: &str) -> Result<PathBuf> {
+        cache::namespace_dir(&Self::cache_dir()?, namespace)
+    }
+
+    /// Evict old and least recently used cache entries
+    ///
+    /// Entries unused for longer than [`CachePolicy::max_age`] are evicted
+    /// first, then the least recently used ones until the cache fits in
+    /// [`CachePolicy::max_bytes`]. With [`CachePolicy::dry_run`] the report
+    /// lists what would be evicted without deleting anything; this also serves
+    /// as size accounting per namespace.
+    pub fn cache_gc(policy: CachePolicy) -> Result<CacheReport> {
+        cache::gc(&Self::cache_dir()?, &policy, std::time::SystemTime::now())
     }
 
     /// Resolve toolset file path with local > user precedence
@@ -312,11 +638,21 @@ impl KodegenConfig {
         toolset::resolve(name)
     }
 
+    /// Resolve and parse a toolset file with local > user precedence
+    ///
+    /// See [`resolve_toolset()`](Self::resolve_toolset) for the search order.
+    /// Unknown fields are rejected; use [`validate_toolset_file()`] for
+    /// editor-style diagnostics including unknown tool and category names.
+    pub fn load_toolset(name: &str) -> Result<Toolset> {
+        toolset::load(name)
+    }
+
     /// Resolve config file path with local > user precedence
     ///
     /// **Search order**:
-    /// 1. `${git_root}/.kodegen/{filename}`
-    /// 2. `$XDG_CONFIG_HOME/kodegen/{filename}`
+    /// 1. `${git_root}/.kodegen/local/{filename}` (private)
+    /// 2. `${git_root}/.kodegen/{filename}`
+    /// 3. `$XDG_CONFIG_HOME/kodegen/{filename}`
     ///
     /// # Errors
     ///
@@ -325,8 +661,8 @@ impl KodegenConfig {
     pub fn resolve_config_file(filename: &str) -> Result<PathBuf> {
         let mut searched_paths = Vec::new();
 
-        // Check local first
-        if let Ok(local_dir) = Self::local_config_dir() {
+        // Check local layers first (private, then shared)
+        for local_dir in local_layers() {
             let local_path = local_dir.join(filename);
             searched_paths.push(local_path.display().to_string());
             if let Some(path) = try_resolve_in_dir(&local_dir, "", filename) {
@@ -335,7 +671,7 @@ impl KodegenConfig {
         }
 
         // Check user global
-        let user_dir = Self::user_config_dir()?;
+        let user_dir = Self::user_layer_dir()?;
         let user_path = user_dir.join(filename);
         searched_paths.push(user_path.display().to_string());
         if let Some(path) = try_resolve_in_dir(&user_dir, "", filename) {
@@ -349,14 +685,173 @@ impl KodegenConfig {
         ))
     }
 
+    /// Like [`resolve_config_file()`](Self::resolve_config_file), but `None`
+    /// only when no layer has the file; other failures are errors
+    pub(crate) fn find_config_file(filename: &str) -> Result<Option<PathBuf>> {
+        let mut layers = local_layers();
+        layers.push(Self::user_layer_dir()?);
+        find_in_layers(&layers, filename)
+    }
+
+    /// Open the encrypted secrets store
+    ///
+    /// **Returns**: store rooted at `{root}/data/secrets/`
+    ///
+    /// Secrets in the git-local `.kodegen/local/secrets/` layer are refused unless
+    /// allowed via [`SecretStore::allow_local`] or `KODEGEN_ALLOW_LOCAL_SECRETS=1`.
+    pub fn secrets() -> Result<SecretStore> {
+        SecretStore::open()
+    }
+
+    /// Load a config file with local > user precedence
+    ///
+    /// The file is resolved via [`resolve_config_file()`](Self::resolve_config_file),
+    /// migrated to the current format version in memory, variable references in
+    /// string values are interpolated, and the result is
+    /// deserialized into `T` (use `serde_json::Value` for untyped access).
+    ///
+    /// **Supported references**: `${env:NAME}`, `${env:NAME:-fallback}`,
+    /// `${git_root}`, `${pwd}`, `${home}`, `${user_config_dir}`, `${config_dir}`,
+    /// `${data_dir}`, `${cache_dir}`, `${secret:NAME}` and `${config:dotted.key}`.
+    /// Use `$${` for a literal `${`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file is missing, is not valid JSON, contains an
+    /// unresolvable or cyclic reference (the error names the file and key), or
+    /// does not match `T`.
+    pub fn load_config<T: serde::de::DeserializeOwned>(filename: &str) -> Result<T> {
+        loader::load(filename, &LoadOptions::default())
+    }
+
+    /// Load a config file with explicit [`LoadOptions`]
+    ///
+    /// Set [`LoadOptions::write_back`] to persist migrations (see
+    /// [`register_migration()`]) instead of re-running them on every load.
+    pub fn load_config_with<T: serde::de::DeserializeOwned>(filename: &str, options: &LoadOptions) -> Result<T> {
+        loader::load(filename, options)
+    }
+
+    /// Migrate a config file to the current format version
+    ///
+    /// Resolves `filename` with local > user precedence and runs the registered
+    /// migrations (see [`register_migration()`]). Unless
+    /// [`MigrateOptions::dry_run`] is set, the original file is backed up to
+    /// `{root}/data/backups/{filename}.{timestamp}.bak` and the migrated file is
+    /// written back. The report includes a unified diff of the change.
+    pub fn migrate_config(filename: &str, options: &MigrateOptions) -> Result<MigrationReport> {
+        loader::migrate(filename, options)
+    }
+
+    /// Move legacy user config roots into [`user_config_dir()`](Self::user_config_dir)
+    ///
+    /// **Legacy roots**: `~/.kodegen`, `~/.config/kodegen` and the platform
+    /// config dir (e.g. `~/Library/Application Support/kodegen`), whichever
+    /// exist and differ from the current root.
+    ///
+    /// Files missing from the current root are moved, identical files are
+    /// merged, and differing files are left in place and reported as conflicts.
+    /// A marker file (`{root}/.legacy-migrated.json`) records the migration so
+    /// it only runs once unless [`LegacyMigrationOptions::force`] is set.
+    pub fn migrate_legacy_locations(options: &LegacyMigrationOptions) -> Result<LegacyMigrationReport> {
+        legacy::migrate(options)
+    }
+
     /// Initialize directory structures for both local and user config
     ///
+    /// Legacy config locations are left alone; move them explicitly with
+    /// [`migrate_legacy_locations()`](Self::migrate_legacy_locations) or
+    /// [`InitOptions::migrate_legacy`].
+    ///
     /// Creates:
     /// - User config: `toolset/`, `claude/` subdirectories + `.gitignore`
-    /// - User state: `logs/` subdirectory
+    /// - User state: `state/`, `logs/`, `cache/` and `bin/` subdirectories
     /// - User data: `stats/`, `memory/` subdirectories
-    /// - Local config (if in git repo): `toolset/`, `claude/` + adds to `.gitignore`
+    /// - Local config (if in git repo): `toolset/`, `claude/`, `local/` + an ignore rule in
+    ///   `.git/info/exclude` (or `.gitignore`, see [`InitOptions::ignore_file`]) for all of
+    ///   `.kodegen/`, or only `.kodegen/local/` with [`InitOptions::shared`]
+    /// - Both: JSON Schemas in `schemas/`, plus `$schema` references in existing
+    ///   toolset and registered config files
     pub fn init_structure() -> Result<()> {
         init::create_directory_structure()
     }
+
+    /// Build the initialization plan without executing it
+    ///
+    /// Lists every directory to create, file to write and `.gitignore` edit
+    /// that [`init_structure_with()`](Self::init_structure_with) would perform.
+    pub fn init_plan(options: &InitOptions) -> Result<InitPlan> {
+        InitPlan::build(options)
+    }
+
+    /// Initialize directory structures with options, returning a structured report
+    ///
+    /// With [`InitOptions::dry_run`] nothing is changed and each action is
+    /// reported as planned or skipped. Otherwise actions run in order and stop
+    /// at the first failure; the report records what was done, what was
+    /// skipped (with the reason) and where it stopped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error only if the plan cannot be built (e.g. the user config
+    /// directory cannot be determined). Failed actions are reported in the
+    /// [`InitReport`]; use [`InitReport::into_result()`] to turn them into an error.
+    pub fn init_structure_with(options: InitOptions) -> Result<InitReport> {
+        init::create_directory_structure_with(&options)
+    }
+
+    /// List project templates usable with [`InitOptions::template`]
+    ///
+    /// Built-in templates ([`BUILTIN_TEMPLATES`]) plus every directory in
+    /// `user_config_dir()/templates/`, sorted by name.
+    pub fn list_templates() -> Result<Vec<String>> {
+        templates::template_names()
+    }
+
+    /// Remove the `.kodegen/` ignore rules added by init from the current repository
+    ///
+    /// Both the tracked `.gitignore` and `.git/info/exclude` are cleaned. Only
+    /// the lines init recorded adding (in `.git/info/kodegen-ignore-rules.json`)
+    /// are removed; identical lines written by hand are kept. Returns the
+    /// files that were (or, with `dry_run`, would be) changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if not in a git repository or an ignore file is a
+    /// symlink or cannot be rewritten.
+    pub fn remove_ignore_rules(dry_run: bool) -> Result<Vec<PathBuf>> {
+        init::remove_ignore_rules(&git::find_git_root()?, dry_run)
+    }
+
+    /// Remove what [`init_structure()`](Self::init_structure) created
+    ///
+    /// Depending on [`TeardownOptions::scope`] removes `cache/`, `cache/` and
+    /// `state/`, or the whole user config root. With
+    /// [`TeardownOptions::ignore_rules`] the `.kodegen/` ignore rules init added
+    /// to the current repository are removed too. The local `.kodegen/`
+    /// directory is kept.
+    ///
+    /// Options default to a dry-run: the report lists every planned removal so
+    /// it can be shown for confirmation before running with `dry_run: false`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the plan cannot be built, e.g. the user root
+    /// resolves to `/` or the home directory. Failures while removing are
+    /// recorded in the report.
+    pub fn teardown(options: TeardownOptions) -> Result<InitReport> {
+        teardown::teardown(&options)
+    }
+
+    /// Check and repair the full directory layout (`kodegen doctor --fix`)
+    ///
+    /// Runs [`init_structure_with()`](Self::init_structure_with) in repair mode:
+    /// missing directories are created, files or dangling symlinks occupying an
+    /// expected directory are moved to `{root}/.quarantine/{timestamp}/`, and
+    /// anything that cannot be fixed is listed in [`InitReport::problems`].
+    /// With `dry_run` the report only describes what would be done
+    /// (`kodegen doctor`).
+    pub fn repair_structure(dry_run: bool) -> Result<InitReport> {
+        init::create_directory_structure_with(&InitOptions { dry_run, repair: true, ..Default::default() })
+    }
 }
@@ -0,0 +1,117 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
+use crate::interpolate::{self, SystemVariables};
+use crate::secrets::SecretStore;
+use crate::versioning::{self, MigrateOptions, MigrationReport};
+
+/// Options controlling how config files are loaded
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Allow `${secret:..}` references to resolve from the git-local
+    /// `.kodegen/local/secrets/` layer (refused by default, since its ignore rule may be missing)
+    pub allow_local_secrets: bool,
+
+    /// Write migrated files back to disk (original backed up to `data_dir()/backups/`)
+    ///
+    /// When `false`, outdated files are migrated in memory on every load.
+    pub write_back: bool,
+}
+
+/// Load a config file with local > user precedence, migrate and interpolate it
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be found or read, is not valid JSON,
+/// cannot be migrated to the current version, contains a reference that
+/// cannot be resolved, or does not match `T`.
+pub fn load<T: DeserializeOwned>(filename: &str, options: &LoadOptions) -> Result<T> {
+    let path = crate::KodegenConfig::resolve_config_file(filename)?;
+    let store = SecretStore::open()?.allow_local(options.allow_local_secrets);
+    load_from_path(&path, &store, options.write_back)
+}
+
+/// Migrate a config file to the current version, optionally as a dry-run
+pub fn migrate(filename: &str, options: &MigrateOptions) -> Result<MigrationReport> {
+    let path = crate::KodegenConfig::resolve_config_file(filename)?;
+    let value = read_json(&path)?;
+    let backup_dir = crate::KodegenConfig::data_dir()?.join("backups");
+    versioning::migrate_file(&path, &value, &backup_dir, options.dry_run)
+}
+
+/// Load a specific config file path using the given secret store
+pub(crate) fn load_from_path<T: DeserializeOwned>(path: &Path, store: &SecretStore, write_back: bool) -> Result<T> {
+    let original = read_json(path)?;
+    let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+
+    let (mut value, from_version, to_version) = versioning::migrate_value(filename, original.clone())
+        .with_context(|| format!("Failed to migrate config file: {}", path.display()))?;
+    if write_back && to_version != from_version {
+        let backup_dir = crate::KodegenConfig::data_dir()?.join("backups");
+        versioning::migrate_file(path, &original, &backup_dir, false)?;
+    }
+
+    // `$schema` is editor metadata, and `version` of a versioned file is
+    // migration bookkeeping; neither is configuration
+    if let Some(map) = value.as_object_mut() {
+        map.remove("$schema");
+        if versioning::is_versioned(filename) {
+            map.remove(versioning::VERSION_FIELD);
+        }
+    }
+
+    interpolate::interpolate_document(&mut value, path, &SystemVariables { store })?;
+
+    serde_json::from_value(value)
+        .with_context(|| format!("Config file has unexpected structure: {}", path.display()))
+}
+
+fn read_json(path: &Path) -> Result<serde_json::Value> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Invalid JSON in config file: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    struct Daemon {
+        bind: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Plugin {
+        version: String,
+    }
+
+    #[test]
+    fn test_version_field_only_managed_for_versioned_files() {
+        versioning::register_migration("test_loader_daemon.json", 1, |mut value| {
+            if let Some(map) = value.as_object_mut()
+                && let Some(listen) = map.remove("listen")
+            {
+                map.insert("bind".to_string(), listen);
+            }
+            Ok(value)
+        });
+
+        let temp = tempfile::tempdir().unwrap();
+        let store = SecretStore::with_dirs(temp.path().join("secrets"), None);
+
+        let daemon = temp.path().join("test_loader_daemon.json");
+        std::fs::write(&daemon, r#"{ "$schema": "x", "listen": "127.0.0.1" }"#).unwrap();
+        let loaded: Daemon = load_from_path(&daemon, &store, false).unwrap();
+        assert_eq!(loaded, Daemon { bind: "127.0.0.1".to_string() });
+
+        let plugin = temp.path().join("test_loader_plugin.json");
+        std::fs::write(&plugin, r#"{ "version": "0.1.0" }"#).unwrap();
+        let loaded: Plugin = load_from_path(&plugin, &store, false).unwrap();
+        assert_eq!(loaded, Plugin { version: "0.1.0".to_string() });
+    }
+}
@@ -0,0 +1,522 @@
+//! Log file naming, rotation and retention under `log_dir()`
+//!
+//! **Naming**: each service logs to `{log_dir}/{category}/{service}.log`,
+//! e.g. `logs/browser/chromium.log`. Rotated files are renamed to
+//! `{service}.{timestamp}.log` and, with [`LogRetention::compress`], gzipped
+//! to `{service}.{timestamp}.log.gz`.
+//!
+//! **Rotation** happens when the active file exceeds
+//! [`LogRetention::max_file_bytes`] or, with [`LogRetention::rotate_daily`],
+//! when the local date changes. After each rotation the whole log directory is
+//! pruned by [`LogRetention::max_age`] and [`LogRetention::max_total_bytes`];
+//! active files are never pruned. Rotation triggered by a write only renames
+//! the file; compression and pruning run on a background thread, and their
+//! failures are reported on stderr rather than failing the write. While a
+//! background run is still busy, the next one is skipped: each run compresses
+//! every rotated file of the service still waiting for it. Compression writes
+//! to `{file}.gz.tmp` and renames it into place, so an interrupted run never
+//! leaves a truncated `.gz`. Several services prune the same directory, so
+//! files that vanish meanwhile are treated as already pruned.
+//!
+//! [`RotatingLogWriter`] implements [`std::io::Write`] for loggers that accept
+//! a writer (e.g. `env_logger::Target::Pipe`). [`FileLogger`] is a ready-made
+//! [`log::Log`] implementation on top of it.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local, NaiveDate};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+/// Extension of active and rotated log files
+const LOG_EXTENSION: &str = "log";
+
+/// Extension appended to compressed rotated log files
+const GZIP_EXTENSION: &str = "gz";
+
+/// Timestamp format of rotated log files (`20240131-235959.123`)
+const ROTATED_TIMESTAMP: &str = "%Y%m%d-%H%M%S%.3f";
+
+/// Retention policy for log files
+#[derive(Debug, Clone)]
+pub struct LogRetention {
+    /// Rotate the active file once it grows beyond this size
+    pub max_file_bytes: Option<u64>,
+    /// Rotate the active file when the local date changes
+    pub rotate_daily: bool,
+    /// Gzip rotated files
+    pub compress: bool,
+    /// Delete rotated files older than this
+    pub max_age: Option<Duration>,
+    /// Delete the oldest rotated files until all logs fit in this size
+    pub max_total_bytes: Option<u64>,
+}
+
+impl Default for LogRetention {
+    fn default() -> Self {
+        Self {
+            max_file_bytes: Some(10 * 1024 * 1024),
+            rotate_daily: true,
+            compress: true,
+            max_age: Some(Duration::from_secs(14 * 24 * 60 * 60)),
+            max_total_bytes: Some(500 * 1024 * 1024),
+        }
+    }
+}
+
+/// Path of a service's active log file: `{log_dir}/{category}/{service}.log`
+///
+/// # Errors
+///
+/// Returns an error if `category` or `service` is not a valid name.
+pub(crate) fn log_file(log_dir: &Path, category: &str, service: &str) -> Result<PathBuf> {
+    for name in [category, service] {
+        crate::validation::validate_name(name).map_err(|e| anyhow!("Invalid log name: {}", e))?;
+    }
+    Ok(log_dir.join(category).join(format!("{}.{}", service, LOG_EXTENSION)))
+}
+
+/// Whether a file name is a rotated (not active) log file
+///
+/// Rotated files end in `.{timestamp}.log` or `.{timestamp}.log.gz`, so
+/// service names containing dots are not mistaken for rotated files.
+fn is_rotated(name: &str) -> bool {
+    let base = name.strip_suffix(&format!(".{}", GZIP_EXTENSION)).unwrap_or(name);
+    let Some(base) = base.strip_suffix(&format!(".{}", LOG_EXTENSION)) else {
+        return false;
+    };
+    base.rsplit_once('.')
+        .and_then(|(rest, millis)| rest.rsplit_once('.').map(|(_, time)| format!("{}.{}", time, millis)))
+        .is_some_and(|timestamp| chrono::NaiveDateTime::parse_from_str(&timestamp, ROTATED_TIMESTAMP).is_ok())
+}
+
+/// Size-, date- and retention-aware log file writer
+///
+/// Writes are appended to the active file; rotation is checked before each
+/// write. The writer is not internally synchronized - wrap it in a `Mutex`
+/// to share it (as [`FileLogger`] does).
+#[derive(Debug)]
+pub struct RotatingLogWriter {
+    path: PathBuf,
+    log_dir: PathBuf,
+    retention: LogRetention,
+    file: File,
+    size: u64,
+    opened_on: NaiveDate,
+    /// Compression and pruning after the last write-triggered rotation
+    maintenance: Option<JoinHandle<()>>,
+}
+
+impl RotatingLogWriter {
+    /// Open (or create) an active log file
+    ///
+    /// `log_dir` is the retention root pruned after each rotation.
+    pub fn open(path: PathBuf, log_dir: PathBuf, retention: LogRetention) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create log directory: {}", dir.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open log file: {}", path.display()))?;
+        let metadata = file.metadata()?;
+        let opened_on = metadata
+            .modified()
+            .map(|t| DateTime::<Local>::from(t).date_naive())
+            .unwrap_or_else(|_| Local::now().date_naive());
+
+        Ok(Self { path, log_dir, retention, file, size: metadata.len(), opened_on, maintenance: None })
+    }
+
+    /// Path of the active log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Rotate now, regardless of size and date
+    ///
+    /// Unlike rotation triggered by a write, this waits for background
+    /// maintenance, then compresses and prunes before returning and reports
+    /// their errors.
+    pub fn rotate(&mut self) -> Result<()> {
+        self.finish_maintenance();
+        self.roll_over()?;
+        if self.retention.compress {
+            compress_pending(&self.path)?;
+        }
+        prune(&self.log_dir, &self.retention, SystemTime::now())?;
+        Ok(())
+    }
+
+    /// Rename the active file aside and reopen it
+    fn roll_over(&mut self) -> Result<()> {
+        self.file.flush()?;
+        if self.size > 0 {
+            let rotated = rotated_path(&self.path, Local::now());
+            fs::rename(&self.path, &rotated)
+                .with_context(|| format!("Failed to rotate log file: {}", self.path.display()))?;
+        }
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        self.opened_on = Local::now().date_naive();
+        Ok(())
+    }
+
+    /// Rotation from the write path: compression and pruning run in the background
+    ///
+    /// Never waits for a previous run: if one is still busy, no new run starts
+    /// and the file rotated now is compressed by the next one. The background
+    /// thread reports errors on stderr, never through `log`: this writer may
+    /// be the global logger, whose lock the writing thread holds.
+    fn rotate_in_background(&mut self) -> Result<()> {
+        self.roll_over()?;
+        if self.maintenance.as_ref().is_some_and(|handle| !handle.is_finished()) {
+            return Ok(());
+        }
+        self.finish_maintenance();
+
+        let active = self.path.clone();
+        let log_dir = self.log_dir.clone();
+        let retention = self.retention.clone();
+        self.maintenance = Some(std::thread::spawn(move || {
+            if retention.compress
+                && let Err(e) = compress_pending(&active)
+            {
+                eprintln!("kodegen: failed to compress rotated logs of {}: {:#}", active.display(), e);
+            }
+            if let Err(e) = prune(&log_dir, &retention, SystemTime::now()) {
+                eprintln!("kodegen: failed to prune logs in {}: {:#}", log_dir.display(), e);
+            }
+        }));
+        Ok(())
+    }
+
+    /// Wait for background compression and pruning to finish
+    fn finish_maintenance(&mut self) {
+        if let Some(handle) = self.maintenance.take() {
+            let _ = handle.join();
+        }
+    }
+
+    fn needs_rotation(&self, incoming: usize) -> bool {
+        let too_big = self
+            .retention
+            .max_file_bytes
+            .is_some_and(|max| self.size > 0 && self.size + incoming as u64 > max);
+        let new_day = self.retention.rotate_daily && self.opened_on != Local::now().date_naive();
+        too_big || new_day
+    }
+}
+
+impl Write for RotatingLogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A failed rotation keeps appending to the active file rather than losing the line
+        if self.needs_rotation(buf.len())
+            && let Err(e) = self.rotate_in_background()
+        {
+            eprintln!("kodegen: failed to rotate log {}: {:#}", self.path.display(), e);
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Waits for background compression, so dropping the writer never cuts it short
+impl Drop for RotatingLogWriter {
+    fn drop(&mut self) {
+        self.finish_maintenance();
+    }
+}
+
+/// `{dir}/{service}.{timestamp}.log` for an active `{dir}/{service}.log`
+fn rotated_path(active: &Path, now: DateTime<Local>) -> PathBuf {
+    let stem = active.file_stem().and_then(|s| s.to_str()).unwrap_or("log");
+    active.with_file_name(format!("{}.{}.{}", stem, now.format(ROTATED_TIMESTAMP), LOG_EXTENSION))
+}
+
+/// Gzip every rotated, still uncompressed file of the service logging to `active`
+fn compress_pending(active: &Path) -> Result<()> {
+    let (Some(dir), Some(stem)) = (active.parent(), active.file_stem().and_then(|s| s.to_str())) else {
+        return Ok(());
+    };
+    let prefix = format!("{}.", stem);
+    let suffix = format!(".{}", LOG_EXTENSION);
+    for entry in fs::read_dir(dir)? {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        // `{stem}.{timestamp}.log` exactly, not another service whose name extends this one
+        let pending = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(&prefix)?.strip_suffix(&suffix))
+            .is_some_and(|timestamp| chrono::NaiveDateTime::parse_from_str(timestamp, ROTATED_TIMESTAMP).is_ok());
+        if pending {
+            compress(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Gzip a file to `{file}.gz` and remove the original
+///
+/// The archive is written to `{file}.gz.tmp` and renamed into place, so an
+/// interrupted run leaves the original to be compressed again. A file pruned
+/// by another process before it could be compressed is skipped.
+fn compress(path: &Path) -> Result<PathBuf> {
+    let target = PathBuf::from(format!("{}.{}", path.display(), GZIP_EXTENSION));
+    let temp = PathBuf::from(format!("{}.tmp", target.display()));
+    let mut input = match File::open(path) {
+        Ok(input) => input,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(target),
+        Err(e) => return Err(e.into()),
+    };
+    let output = File::create(&temp)
+        .with_context(|| format!("Failed to create compressed log: {}", temp.display()))?;
+    let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::rename(&temp, &target)
+        .with_context(|| format!("Failed to move compressed log into place: {}", target.display()))?;
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    Ok(target)
+}
+
+/// Result of pruning the log directory
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogPruneReport {
+    /// Rotated files deleted
+    pub removed: Vec<PathBuf>,
+    /// Bytes freed
+    pub freed_bytes: u64,
+}
+
+/// Delete rotated log files by age, then oldest-first until under the total size limit
+pub(crate) fn prune(log_dir: &Path, retention: &LogRetention, now: SystemTime) -> Result<LogPruneReport> {
+    let mut total = 0;
+    let mut rotated = Vec::new();
+    collect(log_dir, &mut total, &mut rotated)?;
+    rotated.sort_by_key(|(_, _, modified)| *modified);
+
+    let mut report = LogPruneReport::default();
+    for (path, bytes, modified) in rotated {
+        let expired = retention
+            .max_age
+            .is_some_and(|max_age| now.duration_since(modified).unwrap_or_default() > max_age);
+        let over_size = retention.max_total_bytes.is_some_and(|max| total > max);
+        if !(expired || over_size) {
+            continue;
+        }
+        match fs::remove_file(&path) {
+            // Pruned concurrently by another service
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                total = total.saturating_sub(bytes);
+                continue;
+            }
+            result => result.with_context(|| format!("Failed to remove old log: {}", path.display()))?,
+        }
+        total = total.saturating_sub(bytes);
+        report.freed_bytes += bytes;
+        report.removed.push(path);
+    }
+    Ok(report)
+}
+
+/// Walk the log directory, summing all log sizes and collecting rotated files
+fn collect(dir: &Path, total: &mut u64, rotated: &mut Vec<(PathBuf, u64, SystemTime)>) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read log directory: {}", dir.display())),
+    };
+    for entry in entries {
+        let (entry, file_type) = match entry.and_then(|entry| entry.file_type().map(|t| (entry, t))) {
+            Ok(found) => found,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if file_type.is_dir() {
+            collect(&entry.path(), total, rotated)?;
+        } else if file_type.is_file() {
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            *total += metadata.len();
+            if entry.file_name().to_str().is_some_and(is_rotated) {
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                rotated.push((entry.path(), metadata.len(), modified));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// [`log::Log`] implementation writing to a [`RotatingLogWriter`]
+///
+/// Lines are formatted as `{rfc3339 timestamp} {LEVEL} {target}: {message}`.
+#[derive(Debug)]
+pub struct FileLogger {
+    writer: parking_lot::Mutex<RotatingLogWriter>,
+    level: log::LevelFilter,
+}
+
+impl FileLogger {
+    /// Create a logger for records at or above `level`
+    pub fn new(writer: RotatingLogWriter, level: log::LevelFilter) -> Self {
+        Self { writer: parking_lot::Mutex::new(writer), level }
+    }
+
+    /// Install as the global logger
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a global logger is already set.
+    pub fn init(self) -> Result<()> {
+        let level = self.level;
+        log::set_logger(Box::leak(Box::new(self))).map_err(|e| anyhow!("Failed to install logger: {}", e))?;
+        log::set_max_level(level);
+        Ok(())
+    }
+}
+
+impl log::Log for FileLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "{} {} {}: {}\n",
+            Local::now().to_rfc3339(),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        // Logging must never panic or recurse into the logger; drop the line on error
+        let _ = self.writer.lock().write_all(line.as_bytes());
+    }
+
+    fn flush(&self) {
+        let _ = self.writer.lock().flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_size_rotation_compresses_and_prunes() {
+        let temp = tempfile::tempdir().unwrap();
+        let log_dir = temp.path().to_path_buf();
+        let path = log_file(&log_dir, "browser", "chromium").unwrap();
+        let retention = LogRetention {
+            max_file_bytes: Some(10),
+            rotate_daily: false,
+            compress: true,
+            max_age: None,
+            max_total_bytes: None,
+        };
+
+        let mut writer = RotatingLogWriter::open(path.clone(), log_dir.clone(), retention).unwrap();
+        writer.write_all(b"first line\n").unwrap();
+        writer.write_all(b"second\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+        writer.finish_maintenance();
+
+        let rotated: Vec<_> = fs::read_dir(log_dir.join("browser"))
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p != &path)
+            .collect();
+        assert_eq!(rotated.len(), 1);
+        let name = rotated[0].file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("chromium.") && name.ends_with(".log.gz") && is_rotated(name), "{}", name);
+
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(File::open(&rotated[0]).unwrap()).read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, "first line\n");
+    }
+
+    #[test]
+    fn test_interrupted_compression_is_redone() {
+        let temp = tempfile::tempdir().unwrap();
+        let log_dir = temp.path().to_path_buf();
+        let path = log_file(&log_dir, "browser", "chromium").unwrap();
+        let retention = LogRetention { rotate_daily: false, max_age: None, max_total_bytes: None, ..Default::default() };
+        let mut writer = RotatingLogWriter::open(path.clone(), log_dir.clone(), retention).unwrap();
+
+        // Left behind by a run cut short: the original and a partial archive
+        let dir = path.parent().unwrap();
+        let pending = dir.join("chromium.20240101-000000.000.log");
+        fs::write(&pending, "earlier\n").unwrap();
+        fs::write(dir.join("chromium.20240101-000000.000.log.gz.tmp"), "partial").unwrap();
+        // Another service whose name extends this one is not ours to compress
+        let other = dir.join("chromium.beta.20240101-000000.000.log");
+        fs::write(&other, "other\n").unwrap();
+
+        writer.rotate().unwrap();
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(File::open(dir.join("chromium.20240101-000000.000.log.gz")).unwrap())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "earlier\n");
+        assert!(!pending.exists() && !dir.join("chromium.20240101-000000.000.log.gz.tmp").exists());
+        assert!(other.exists());
+    }
+
+    #[test]
+    fn test_prune_failure_does_not_fail_write() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("git/server.log");
+        // A retention root that cannot be read makes pruning fail
+        let log_dir = temp.path().join("not-a-dir");
+        fs::write(&log_dir, "").unwrap();
+        let retention = LogRetention { max_file_bytes: Some(4), rotate_daily: false, compress: false, ..Default::default() };
+
+        let mut writer = RotatingLogWriter::open(path.clone(), log_dir.clone(), retention).unwrap();
+        writer.write_all(b"one\n").unwrap();
+        writer.write_all(b"two\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "two\n");
+        writer.finish_maintenance();
+        assert!(writer.rotate().is_err(), "explicit rotation reports the pruning error");
+    }
+
+    #[test]
+    fn test_prune_keeps_active_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("git");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("server.log"), vec![b'x'; 100]).unwrap();
+        fs::write(dir.join("server.20240101-000000.000.log.gz"), vec![b'x'; 100]).unwrap();
+        fs::write(dir.join("server.20240102-000000.000.log"), vec![b'x'; 100]).unwrap();
+
+        let retention = LogRetention { max_age: None, max_total_bytes: Some(150), ..Default::default() };
+        let report = prune(temp.path(), &retention, SystemTime::now()).unwrap();
+        assert_eq!(report.removed.len(), 2);
+        assert!(dir.join("server.log").exists());
+        assert!(!is_rotated("server.log"));
+        assert!(!is_rotated("kodegen.daemon.log"));
+    }
+}
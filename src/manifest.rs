@@ -0,0 +1,80 @@
+//! Machine-readable manifest of the registries in [`constants`](crate::constants)
+//!
+//! Non-Rust tooling (dashboards, shell completions) reads this instead of
+//! duplicating the tables. The manifest contains the static defaults only;
+//! runtime overrides are resolved by [`PortMap`](crate::PortMap).
+//!
+//! ```json
+//! {
+//!   "manifest_version": 1,
+//!   "categories": [{ "name": "git", "icon": "⛙", "server": "git", "port": 30444 }, ...],
+//!   "tools": [{ "name": "git_add", "category": "git", "read_only": false, "destructive": false, "description": "..." }, ...],
+//!   "aliases": { "git_tag": "git_tag_create", ... },
+//!   "ports": { "min": 30438, "max": 30452, "assignments": { "browser": 30438, ... } },
+//!   "headers": { "connection_id": "x-kodegen-connection-id", ... }
+//! }
+//! ```
+//!
+//! [`MANIFEST_VERSION`] is bumped whenever a field is removed or changes
+//! meaning; adding fields or entries does not bump it.
+
+use serde_json::{json, Map, Value};
+
+use crate::constants::{Category, CATEGORY_PORTS, PORT_MAX, PORT_MIN, TOOLS, TOOL_ALIASES};
+
+/// Format version of [`registry_manifest()`]
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Serialize categories, tools, default ports and header names
+pub fn registry_manifest() -> Value {
+    let categories: Vec<Value> = Category::all()
+        .iter()
+        .map(|category| {
+            json!({
+                "name": category.name,
+                "icon": category.icon.to_string(),
+                "server": category.backend().map(|c| c.name),
+                "port": category.port(),
+            })
+        })
+        .collect();
+
+    let tools: Vec<Value> = TOOLS
+        .iter()
+        .map(|tool| {
+            json!({
+                "name": tool.name,
+                "category": tool.category,
+                "read_only": tool.read_only,
+                "destructive": tool.destructive,
+                "description": tool.description,
+            })
+        })
+        .collect();
+
+    let aliases: Map<String, Value> =
+        TOOL_ALIASES.iter().map(|(alias, canonical)| (alias.to_string(), json!(canonical))).collect();
+
+    let assignments: Map<String, Value> =
+        CATEGORY_PORTS.iter().map(|(category, port)| (category.name.to_string(), json!(port))).collect();
+
+    json!({
+        "manifest_version": MANIFEST_VERSION,
+        "categories": categories,
+        "tools": tools,
+        "aliases": aliases,
+        "ports": {
+            "min": PORT_MIN,
+            "max": PORT_MAX,
+            "assignments": assignments,
+        },
+        "headers": {
+            "connection_id": crate::X_KODEGEN_CONNECTION_ID,
+            "pwd": crate::X_KODEGEN_PWD,
+            "gitroot": crate::X_KODEGEN_GITROOT,
+            "timestamp": crate::X_KODEGEN_TIMESTAMP,
+            "nonce": crate::X_KODEGEN_NONCE,
+            "signature": crate::X_KODEGEN_SIGNATURE,
+        },
+    })
+}
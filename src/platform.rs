@@ -238,12 +238,13 @@ pub fn user_config_dir() -> Result<PathBuf> {
 pub fn user_config_dir() -> Result<PathBuf> {
     // macOS doesn't typically use XDG_CONFIG_HOME, but respect it if set
     if is_custom_paths_allowed()
-        && let Ok(custom_path) = std::env::var("XDG_CONFIG_HOME") {
-            warn!(
-                "KODEGEN_ALLOW_CUSTOM_PATHS is enabled - bypassing validation for XDG_CONFIG_HOME (UNSAFE)"
-            );
-            return Ok(PathBuf::from(custom_path).join("kodegen"));
-        }
+        && let Ok(custom_path) = std::env::var("XDG_CONFIG_HOME")
+    {
+        warn!(
+            "KODEGEN_ALLOW_CUSTOM_PATHS is enabled - bypassing validation for XDG_CONFIG_HOME (UNSAFE)"
+        );
+        return Ok(PathBuf::from(custom_path).join("kodegen"));
+    }
     
     let validated = std::env::var("XDG_CONFIG_HOME")
         .ok()
@@ -269,13 +270,13 @@ pub fn user_config_dir() -> Result<PathBuf> {
 
 #[cfg(not(any(target_os = "windows", target_os = "macos")))]
 pub fn user_config_dir() -> Result<PathBuf> {
-    if is_custom_paths_allowed() {
-        if let Ok(custom_path) = std::env::var("XDG_CONFIG_HOME") {
-            warn!(
-                "KODEGEN_ALLOW_CUSTOM_PATHS is enabled - bypassing validation for XDG_CONFIG_HOME (UNSAFE)"
-            );
-            return Ok(PathBuf::from(custom_path).join("kodegen"));
-        }
+    if is_custom_paths_allowed()
+        && let Ok(custom_path) = std::env::var("XDG_CONFIG_HOME")
+    {
+        warn!(
+            "KODEGEN_ALLOW_CUSTOM_PATHS is enabled - bypassing validation for XDG_CONFIG_HOME (UNSAFE)"
+        );
+        return Ok(PathBuf::from(custom_path).join("kodegen"));
     }
     
     let validated = std::env::var("XDG_CONFIG_HOME")
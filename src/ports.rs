@@ -0,0 +1,390 @@
+//! Runtime port assignments
+//!
+//! [`CATEGORY_PORTS`] is the default table. A [`PortMap`] starts from it and
+//! applies overrides, highest precedence first:
+//!
+//! 1. `KODEGEN_PORT_{CATEGORY}` (e.g. `KODEGEN_PORT_GIT=31006`)
+//! 2. `ports` in `ports.json` (layered: `.kodegen/local/`, `.kodegen/`, user)
+//! 3. `KODEGEN_PORT_BASE`, then `base` in `ports.json`: shifts every default
+//!    port, keeping its offset from [`PORT_MIN`]
+//!
+//! For a named instance (`KODEGEN_INSTANCE`), ports from `ports.json` and the
+//! defaults are shifted by the instance's port offset, since `ports.json` is
+//! shared between instances. Environment overrides are used as given.
+//!
+//! ```json
+//! {
+//!   "base": 31000,
+//!   "ports": { "git": 31100 }
+//! }
+//! ```
+//!
+//! The result is validated: no two services may share a port and every port
+//! must lie in `1024..=65535`.
+//!
+//! `ports.json` also selects the [`Transport`] per category: `"transport"`
+//! sets the default and `"transports"` overrides it per category, e.g.
+//! `{ "transport": "unix", "transports": { "browser": "tcp" } }`.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::{Ipv4Addr, TcpListener};
+
+use crate::constants::{Category, CATEGORY_PORTS, PORT_MAX, PORT_MIN};
+
+/// Config file with port overrides
+pub const PORTS_CONFIG_FILE: &str = "ports.json";
+
+/// Environment variable shifting every default port
+pub const PORT_BASE_ENV: &str = "KODEGEN_PORT_BASE";
+
+/// Prefix of per-category port environment variables (`KODEGEN_PORT_{CATEGORY}`)
+pub const PORT_ENV_PREFIX: &str = "KODEGEN_PORT_";
+
+/// Lowest port a service may be assigned (no privileged ports)
+const MIN_ALLOWED_PORT: u16 = 1024;
+
+/// Contents of `ports.json`
+///
+/// Unknown fields are rejected so a typo like `port` fails loudly instead of
+/// silently leaving every service on its default port.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PortConfig {
+    /// First port of the range; the defaults keep their offsets from it
+    #[serde(default)]
+    pub base: Option<u16>,
+    /// Explicit port per category name
+    #[serde(default)]
+    pub ports: BTreeMap<String, u16>,
+    /// Transport of every service unless overridden (default `tcp`)
+    #[serde(default)]
+    pub transport: Option<Transport>,
+    /// Transport per category name
+    #[serde(default)]
+    pub transports: BTreeMap<String, Transport>,
+}
+
+/// How a service is reached
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// HTTP on a loopback TCP port
+    #[default]
+    Tcp,
+    /// HTTP on a Unix domain socket (see [`service_socket_path()`](crate::KodegenConfig::service_socket_path))
+    Unix,
+}
+
+/// Port and transport of every service, after overrides
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortMap {
+    ports: Vec<(&'static Category, u16)>,
+    /// Names of server categories using [`Transport::Unix`]
+    unix: BTreeSet<&'static str>,
+}
+
+impl Default for PortMap {
+    fn default() -> Self {
+        Self { ports: CATEGORY_PORTS.to_vec(), unix: BTreeSet::new() }
+    }
+}
+
+impl PortMap {
+    /// Default assignments from [`CATEGORY_PORTS`]
+    pub fn defaults() -> Self {
+        Self::default()
+    }
+
+    /// Default assignments shifted to start at `base`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the shifted range does not fit in `1024..=65535`.
+    pub fn with_base(base: u16) -> Result<Self> {
+        if base < MIN_ALLOWED_PORT || base.checked_add(PORT_MAX - PORT_MIN).is_none() {
+            bail!(
+                "Invalid port base {}: the {} service ports must fit in {}..=65535",
+                base,
+                PORT_MAX - PORT_MIN + 1,
+                MIN_ALLOWED_PORT
+            );
+        }
+        let ports = CATEGORY_PORTS.iter().map(|&(category, port)| (category, port - PORT_MIN + base)).collect();
+        Ok(Self { ports, unix: BTreeSet::new() })
+    }
+
+    /// Load overrides from `ports.json` and the environment, then validate
+    ///
+    /// A missing `ports.json` means no file overrides.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ports.json` cannot be accessed, `ports.json` or an
+    /// environment variable is invalid, names an unknown category, or the
+    /// resulting map fails [`validate()`](Self::validate).
+    pub fn load() -> Result<Self> {
+        let config = match crate::KodegenConfig::find_config_file(PORTS_CONFIG_FILE)? {
+            Some(_) => crate::KodegenConfig::load_config(PORTS_CONFIG_FILE)?,
+            None => PortConfig::default(),
+        };
+        let offset = crate::instance::port_offset(crate::instance::current()?.as_deref());
+        Self::from_config(&config, offset, |name| std::env::var(name).ok())
+    }
+
+    /// Apply a config (shifted by an instance `offset`) and environment
+    /// overrides (via `env`) to the defaults
+    pub(crate) fn from_config(config: &PortConfig, offset: u16, env: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let shift = |port: u16| {
+            port.checked_add(offset)
+                .ok_or_else(|| anyhow!("Port {} shifted by instance offset {} exceeds 65535", port, offset))
+        };
+        let env_port = |name: &str| -> Result<Option<u16>> {
+            env(name)
+                .map(|value| value.trim().parse().with_context(|| format!("Invalid {}: '{}' is not a port", name, value)))
+                .transpose()
+        };
+
+        let base = match env_port(PORT_BASE_ENV)? {
+            Some(base) => base,
+            None => shift(config.base.unwrap_or(PORT_MIN))?,
+        };
+        let mut map = Self::with_base(base)?;
+        for (name, &port) in &config.ports {
+            let category = Category::from_name(name)
+                .ok_or_else(|| anyhow!("Invalid {}: unknown category '{}'", PORTS_CONFIG_FILE, name))?;
+            map.set(category, shift(port)?)?;
+        }
+        for &(category, _) in CATEGORY_PORTS {
+            let var = format!("{}{}", PORT_ENV_PREFIX, category.name.to_ascii_uppercase());
+            if let Some(port) = env_port(&var)? {
+                map.set(category, port)?;
+            }
+        }
+
+        if let Some(transport) = config.transport {
+            for &(category, _) in CATEGORY_PORTS {
+                map.set_transport(category, transport)?;
+            }
+        }
+        for (name, &transport) in &config.transports {
+            let category = Category::from_name(name)
+                .ok_or_else(|| anyhow!("Invalid {}: unknown category '{}'", PORTS_CONFIG_FILE, name))?;
+            map.set_transport(category, transport)?;
+        }
+
+        map.validate()?;
+        Ok(map)
+    }
+
+    /// Assign a port to the server handling `category`
+    ///
+    /// Call [`validate()`](Self::validate) after a batch of changes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `category` has no server.
+    pub fn set(&mut self, category: &Category, port: u16) -> Result<()> {
+        let backend = category.backend().ok_or_else(|| anyhow!("Unknown category '{}'", category))?;
+        let entry = self
+            .ports
+            .iter_mut()
+            .find(|(c, _)| c.name == backend.name)
+            .ok_or_else(|| anyhow!("Category '{}' has no server port", category))?;
+        entry.1 = port;
+        Ok(())
+    }
+
+    /// Select the transport of the server handling `category`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `category` has no server.
+    pub fn set_transport(&mut self, category: &Category, transport: Transport) -> Result<()> {
+        let backend = category.backend().ok_or_else(|| anyhow!("Unknown category '{}'", category))?;
+        match transport {
+            Transport::Tcp => self.unix.remove(backend.name),
+            Transport::Unix => self.unix.insert(backend.name),
+        };
+        Ok(())
+    }
+
+    /// Transport of the server handling `category`
+    pub fn transport(&self, category: &Category) -> Transport {
+        match category.backend() {
+            Some(backend) if self.unix.contains(backend.name) => Transport::Unix,
+            _ => Transport::Tcp,
+        }
+    }
+
+    /// Port of the server handling `category` (memory resolves to candle-agent)
+    pub fn port(&self, category: &Category) -> Option<u16> {
+        let backend = category.backend()?;
+        self.ports.iter().find(|(c, _)| c.name == backend.name).map(|(_, port)| *port)
+    }
+
+    /// Category whose server is assigned `port`
+    pub fn category_for_port(&self, port: u16) -> Option<&'static Category> {
+        self.ports.iter().find(|(_, p)| *p == port).map(|(category, _)| *category)
+    }
+
+    /// All assignments, in [`CATEGORY_PORTS`] order
+    pub fn iter(&self) -> impl Iterator<Item = (&'static Category, u16)> + '_ {
+        self.ports.iter().copied()
+    }
+
+    /// Check that ports are unique and outside the privileged range
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first conflicting or out-of-range assignment.
+    pub fn validate(&self) -> Result<()> {
+        let mut seen: BTreeMap<u16, &Category> = BTreeMap::new();
+        for &(category, port) in &self.ports {
+            if port < MIN_ALLOWED_PORT {
+                bail!("Invalid port {} for {}: must be in {}..=65535", port, category, MIN_ALLOWED_PORT);
+            }
+            if let Some(other) = seen.insert(port, category) {
+                bail!("Port conflict: {} and {} are both assigned port {}", other, category, port);
+            }
+        }
+        Ok(())
+    }
+
+    /// Assignments whose port is already bound on localhost
+    pub fn unavailable(&self) -> Vec<(&'static Category, u16)> {
+        self.iter().filter(|&(_, port)| !is_port_free(port)).collect()
+    }
+
+    /// Port for `category`, checked to be free before a daemon binds it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `category` has no port or the port is in use.
+    pub fn ensure_available(&self, category: &Category) -> Result<u16> {
+        let port = self.port(category).ok_or_else(|| anyhow!("Category '{}' has no server port", category))?;
+        if !is_port_free(port) {
+            let backend = category.backend().unwrap_or(category);
+            bail!(
+                "Port {} for {} is already in use (set {}{} to override)",
+                port,
+                category,
+                PORT_ENV_PREFIX,
+                backend.name.to_ascii_uppercase()
+            );
+        }
+        Ok(port)
+    }
+}
+
+/// Whether `port` can be bound on the loopback interface
+fn is_port_free(port: u16) -> bool {
+    TcpListener::bind((Ipv4Addr::LOCALHOST, port)).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{CATEGORY_GIT, CATEGORY_MEMORY, CATEGORY_TERMINAL, PORT_CANDLE_AGENT, PORT_GIT, PORT_TERMINAL};
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| vars.iter().find(|(n, _)| *n == name).map(|(_, v)| v.to_string())
+    }
+
+    #[test]
+    fn test_overrides_and_precedence() {
+        let defaults = PortMap::from_config(&PortConfig::default(), 0, env(&[])).unwrap();
+        assert_eq!(defaults, PortMap::defaults());
+        assert_eq!(defaults.port(CATEGORY_MEMORY), Some(PORT_CANDLE_AGENT));
+
+        let config = PortConfig { base: Some(40000), ports: [("git".to_string(), 41000)].into(), ..Default::default() };
+        let map = PortMap::from_config(&config, 0, env(&[])).unwrap();
+        assert_eq!(map.port(CATEGORY_TERMINAL), Some(40000 + (PORT_TERMINAL - PORT_MIN)));
+        assert_eq!(map.port(CATEGORY_GIT), Some(41000));
+        assert_eq!(map.category_for_port(41000), Some(CATEGORY_GIT));
+
+        // Environment wins over the file
+        let map = PortMap::from_config(&config, 0, env(&[(PORT_BASE_ENV, "50000"), ("KODEGEN_PORT_GIT", "51000")])).unwrap();
+        assert_eq!(map.port(CATEGORY_TERMINAL), Some(50000 + (PORT_TERMINAL - PORT_MIN)));
+        assert_eq!(map.port(CATEGORY_GIT), Some(51000));
+    }
+
+    #[test]
+    fn test_config_typos_and_access_errors_are_reported() {
+        let err = serde_json::from_str::<PortConfig>(r#"{ "port": { "git": 41000 } }"#).unwrap_err();
+        assert!(err.to_string().contains("unknown field `port`"), "{}", err);
+
+        let temp = tempfile::tempdir().unwrap();
+        let (local, user) = (temp.path().join("local"), temp.path().join("user"));
+        std::fs::create_dir_all(&user).unwrap();
+        assert_eq!(crate::find_in_layers(&[local.clone(), user.clone()], PORTS_CONFIG_FILE).unwrap(), None);
+
+        std::fs::write(user.join(PORTS_CONFIG_FILE), "{}").unwrap();
+        let found = crate::find_in_layers(&[local.clone(), user.clone()], PORTS_CONFIG_FILE).unwrap();
+        assert_eq!(found, Some(user.join(PORTS_CONFIG_FILE).canonicalize().unwrap()));
+
+        // A layer that exists but cannot hold the file is an error, not a fallback
+        std::fs::write(&local, "not a directory").unwrap();
+        assert!(crate::find_in_layers(&[local, user], PORTS_CONFIG_FILE).is_err());
+    }
+
+    #[test]
+    fn test_instance_offset() {
+        let config = PortConfig { ports: [("git".to_string(), 41000)].into(), ..Default::default() };
+        let map = PortMap::from_config(&config, 300, env(&[])).unwrap();
+        assert_eq!(map.port(CATEGORY_TERMINAL), Some(PORT_TERMINAL + 300));
+        assert_eq!(map.port(CATEGORY_GIT), Some(41300));
+
+        // Per-process environment overrides are not shifted
+        let map = PortMap::from_config(&config, 300, env(&[(PORT_BASE_ENV, "50000"), ("KODEGEN_PORT_GIT", "51000")])).unwrap();
+        assert_eq!(map.port(CATEGORY_TERMINAL), Some(50000 + (PORT_TERMINAL - PORT_MIN)));
+        assert_eq!(map.port(CATEGORY_GIT), Some(51000));
+    }
+
+    #[test]
+    fn test_transport_selection() {
+        let config = PortConfig {
+            transport: Some(Transport::Unix),
+            transports: [("terminal".to_string(), Transport::Tcp)].into(),
+            ..Default::default()
+        };
+        let map = PortMap::from_config(&config, 0, env(&[])).unwrap();
+        assert_eq!(map.transport(CATEGORY_GIT), Transport::Unix);
+        assert_eq!(map.transport(CATEGORY_MEMORY), Transport::Unix);
+        assert_eq!(map.transport(CATEGORY_TERMINAL), Transport::Tcp);
+
+        let parsed: PortConfig = serde_json::from_str(r#"{ "transports": { "git": "unix" } }"#).unwrap();
+        assert_eq!(PortMap::from_config(&parsed, 0, env(&[])).unwrap().transport(CATEGORY_GIT), Transport::Unix);
+        assert!(serde_json::from_str::<PortConfig>(r#"{ "transport": "pipe" }"#).is_err());
+    }
+
+    #[test]
+    fn test_invalid_assignments_rejected() {
+        let conflict = PortConfig { ports: [("git".to_string(), PORT_CANDLE_AGENT)].into(), ..Default::default() };
+        let err = PortMap::from_config(&conflict, 0, env(&[])).unwrap_err();
+        assert!(err.to_string().contains("Port conflict"), "{}", err);
+
+        assert!(PortMap::with_base(65530).is_err());
+        assert!(PortMap::with_base(80).is_err());
+        assert!(PortMap::from_config(&PortConfig::default(), 0, env(&[(PORT_BASE_ENV, "high")])).is_err());
+
+        let unknown = PortConfig { ports: [("gti".to_string(), 40000)].into(), ..Default::default() };
+        assert!(PortMap::from_config(&unknown, 0, env(&[])).unwrap_err().to_string().contains("gti"));
+    }
+
+    #[test]
+    fn test_probe_detects_bound_port() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let mut map = PortMap::defaults();
+        map.set(CATEGORY_GIT, port).unwrap();
+        assert!(map.unavailable().contains(&(CATEGORY_GIT, port)));
+        assert!(map.ensure_available(CATEGORY_GIT).is_err());
+
+        drop(listener);
+        assert_eq!(map.port(CATEGORY_GIT), Some(port));
+        assert_ne!(map.port(CATEGORY_GIT), Some(PORT_GIT));
+    }
+}
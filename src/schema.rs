@@ -0,0 +1,748 @@
+//! JSON Schema generation and validation for toolset and config files
+//!
+//! Schemas are written to `{root}/schemas/` by
+//! [`init_structure()`](crate::KodegenConfig::init_structure), and `$schema`
+//! references are added to existing toolset and config files so editors such
+//! as VS Code offer completion for tool names and categories.
+//!
+//! The validator implements the subset of JSON Schema (draft-07) used by
+//! kodegen schemas: `type`, `enum`, `properties`, `required`,
+//! `additionalProperties`, `items`, `uniqueItems`, `minimum`, `maximum` and
+//! local `$ref`s. Errors carry the file, line and column of the offending
+//! value so they can be reported like compiler diagnostics.
+
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// JSON Schema dialect used for all generated schemas
+const SCHEMA_DIALECT: &str = "http://json-schema.org/draft-07/schema#";
+
+/// Directory (under a config root) holding generated schema files
+pub(crate) const SCHEMAS_DIR: &str = "schemas";
+
+/// Filename of the generated toolset schema
+pub(crate) const TOOLSET_SCHEMA_FILE: &str = "toolset.schema.json";
+
+/// Type alias for the config schema registry
+/// Keyed by config filename (e.g. `daemon.json`)
+type SchemaRegistry = parking_lot::RwLock<BTreeMap<String, Value>>;
+
+/// Global registry of config file schemas
+static CONFIG_SCHEMAS: std::sync::LazyLock<SchemaRegistry> =
+    std::sync::LazyLock::new(|| parking_lot::RwLock::new(BTreeMap::new()));
+
+/// A schema violation located in a source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// File that was validated
+    pub file: PathBuf,
+    /// 1-based line of the offending value or key
+    pub line: usize,
+    /// 1-based column of the offending value or key
+    pub column: usize,
+    /// JSON pointer to the offending value (empty for the document root)
+    pub pointer: String,
+    /// Human-readable description of the violation
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.file.display(), self.line, self.column, self.message)
+    }
+}
+
+/// Generate the JSON Schema for toolset files
+///
+/// Tool names and categories are enums generated from
+/// [`TOOL_NAMES`](crate::TOOL_NAMES) and [`CATEGORIES`](crate::CATEGORIES).
+pub fn toolset_schema() -> Value {
+    let categories: Vec<&str> = crate::CATEGORIES.iter().map(|c| c.name).collect();
+
+    json!({
+        "$schema": SCHEMA_DIALECT,
+        "$id": "https://kodegen.ai/schemas/toolset.schema.json",
+        "title": "KODEGEN toolset",
+        "description": "Set of MCP tools enabled for an agent session",
+        "type": "object",
+        "properties": {
+            "$schema": { "type": "string" },
+            "description": {
+                "type": "string",
+                "description": "Human-readable description of the toolset"
+            },
+            "tools": {
+                "type": "array",
+                "description": "Individual tool names to enable",
+                "items": { "$ref": "#/definitions/tool" },
+                "uniqueItems": true
+            },
+            "categories": {
+                "type": "array",
+                "description": "Categories whose tools are all enabled",
+                "items": { "$ref": "#/definitions/category" },
+                "uniqueItems": true
+            }
+        },
+        "additionalProperties": false,
+        "definitions": {
+            "tool": { "type": "string", "enum": crate::TOOL_NAMES },
+            "category": { "type": "string", "enum": categories }
+        }
+    })
+}
+
+/// Register the JSON Schema for a config file
+///
+/// `filename` is the name passed to
+/// [`resolve_config_file()`](crate::KodegenConfig::resolve_config_file)
+/// (e.g. `daemon.json`). Registering the same filename again replaces the
+/// previous schema. Registered schemas are written by `init_structure()` and
+/// used by [`validate_config_file()`].
+pub fn register_config_schema(filename: &str, schema: Value) {
+    CONFIG_SCHEMAS.write().insert(filename.to_string(), schema);
+}
+
+/// Get the registered schema for a config file
+///
+/// For files with registered migrations (see
+/// [`register_migration()`](crate::register_migration)) the root `version`
+/// property is added unless the schema already defines it.
+pub fn config_schema(filename: &str) -> Option<Value> {
+    let schema = CONFIG_SCHEMAS.read().get(filename).cloned()?;
+    Some(with_version_property(filename, schema))
+}
+
+/// Allow the migration-managed `version` field at the root of a versioned config schema
+fn with_version_property(filename: &str, mut schema: Value) -> Value {
+    if crate::versioning::is_versioned(filename)
+        && let Some(root) = schema.as_object_mut()
+        && let Some(properties) = root.entry("properties").or_insert_with(|| json!({})).as_object_mut()
+    {
+        properties.entry(crate::VERSION_FIELD).or_insert_with(|| {
+            json!({
+                "type": "integer",
+                "minimum": 1,
+                "description": "Config format version, updated by migrations"
+            })
+        });
+    }
+    schema
+}
+
+/// Validate a toolset file against [`toolset_schema()`]
+///
+/// Returns an empty list if the file is valid. JSON syntax errors are reported
+/// as a single [`SchemaError`].
+///
+/// # Errors
+///
+/// Returns an error only if the file cannot be read.
+pub fn validate_toolset_file(path: &Path) -> Result<Vec<SchemaError>> {
+    validate_file(path, &toolset_schema())
+}
+
+/// Validate a config file against its registered schema
+///
+/// The schema is looked up by the file's name (see [`register_config_schema()`]).
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or no schema is registered for it.
+pub fn validate_config_file(path: &Path) -> Result<Vec<SchemaError>> {
+    let filename = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .with_context(|| format!("Invalid config file path: {}", path.display()))?;
+    let schema = config_schema(filename)
+        .with_context(|| format!("No schema registered for config file '{}'", filename))?;
+    validate_file(path, &schema)
+}
+
+/// Validate a JSON file against an arbitrary schema
+pub fn validate_file(path: &Path, schema: &Value) -> Result<Vec<SchemaError>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(validate_str(&content, schema, path))
+}
+
+/// Validate JSON text, attributing errors to `file`
+pub(crate) fn validate_str(content: &str, schema: &Value, file: &Path) -> Vec<SchemaError> {
+    let value: Value = match serde_json::from_str(content) {
+        Ok(value) => value,
+        Err(e) => {
+            return vec![SchemaError {
+                file: file.to_path_buf(),
+                line: e.line(),
+                column: e.column(),
+                pointer: String::new(),
+                message: format!("invalid JSON: {}", e),
+            }];
+        }
+    };
+
+    let mut violations = Vec::new();
+    Validator { root: schema, violations: &mut violations }.validate(&value, schema, "");
+
+    let positions = Positions::scan(content);
+    let mut errors: Vec<SchemaError> = violations
+        .into_iter()
+        .map(|v| {
+            let (line, column) = positions.locate(&v.pointer, v.at_key);
+            SchemaError {
+                file: file.to_path_buf(),
+                line,
+                column,
+                pointer: v.pointer,
+                message: v.message,
+            }
+        })
+        .collect();
+    errors.sort_by_key(|e| (e.line, e.column));
+    errors
+}
+
+/// Schema file name for a config filename (`daemon.json` -> `daemon.schema.json`)
+pub(crate) fn schema_filename(config_filename: &str) -> String {
+    let stem = config_filename.strip_suffix(".json").unwrap_or(config_filename);
+    format!("{}.schema.json", stem)
+}
+
+/// Generated schema files for a config root, as `({root}/schemas/{file}, contents)`
+///
+/// Includes the toolset schema and every registered config schema.
+pub(crate) fn schema_files(root: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let dir = root.join(SCHEMAS_DIR);
+
+    let mut schemas = vec![(TOOLSET_SCHEMA_FILE.to_string(), toolset_schema())];
+    schemas.extend(
+        CONFIG_SCHEMAS
+            .read()
+            .iter()
+            .map(|(filename, schema)| (schema_filename(filename), with_version_property(filename, schema.clone()))),
+    );
+
+    schemas
+        .into_iter()
+        .map(|(filename, schema)| {
+            let mut contents = serde_json::to_string_pretty(&schema)?;
+            contents.push('\n');
+            Ok((dir.join(filename), contents))
+        })
+        .collect()
+}
+
+/// Existing files under `root` that should reference a schema, as `(file, reference)`
+///
+/// - `{root}/toolset/*.json` -> `../schemas/toolset.schema.json`
+/// - `{root}/{filename}` (registered configs) -> `schemas/{stem}.schema.json`
+pub(crate) fn schema_reference_targets(root: &Path) -> Vec<(PathBuf, String)> {
+    let mut targets = Vec::new();
+
+    let toolset_ref = format!("../{}/{}", SCHEMAS_DIR, TOOLSET_SCHEMA_FILE);
+    if let Ok(entries) = fs::read_dir(root.join("toolset")) {
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+        targets.extend(paths.into_iter().map(|path| (path, toolset_ref.clone())));
+    }
+
+    for filename in CONFIG_SCHEMAS.read().keys() {
+        let path = root.join(filename);
+        if path.exists() {
+            targets.push((path, format!("{}/{}", SCHEMAS_DIR, schema_filename(filename))));
+        }
+    }
+    targets
+}
+
+/// Whether a file is a regular JSON object file without a `$schema` property
+pub(crate) fn needs_schema_reference(path: &Path) -> bool {
+    let is_file = fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_file());
+    is_file
+        && fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+            .is_some_and(|value| value.as_object().is_some_and(|map| !map.contains_key("$schema")))
+}
+
+/// Insert a `"$schema"` property as the first key of a JSON object file
+///
+/// The insertion is textual so existing formatting and key order are kept.
+/// Files that already declare `$schema`, are not JSON objects, or are not
+/// regular files are left untouched.
+pub(crate) fn add_schema_reference(path: &Path, reference: &str) -> Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.file_type().is_file() {
+        log::warn!("Not adding $schema to non-regular file: {}", path.display());
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(path)?;
+    match serde_json::from_str::<Value>(&content) {
+        Ok(Value::Object(map)) if !map.contains_key("$schema") => {}
+        _ => return Ok(()),
+    }
+
+    let Some(brace) = content.find('{') else {
+        return Ok(());
+    };
+    let after = &content[brace + 1..];
+    let rest = after.trim_start();
+    let whitespace = &after[..after.len() - rest.len()];
+    let property = format!("\"$schema\": {}", serde_json::to_string(reference)?);
+
+    let inserted = if rest.starts_with('}') {
+        format!("{{{}{}{}", property, whitespace, rest)
+    } else if whitespace.contains('\n') {
+        format!("{{{ws}{},{ws}{}", property, rest, ws = whitespace)
+    } else {
+        format!("{{{}{}, {}", whitespace, property, rest)
+    };
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut temp_file = NamedTempFile::new_in(dir)?;
+    temp_file.write_all(&content.as_bytes()[..brace])?;
+    temp_file.write_all(inserted.as_bytes())?;
+    temp_file.persist(path)?;
+
+    log::info!("Added $schema reference to {}", path.display());
+    Ok(())
+}
+
+/// A violation before it is mapped to a source position
+struct Violation {
+    pointer: String,
+    /// Report the position of the object key rather than its value
+    at_key: bool,
+    message: String,
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    violations: &'a mut Vec<Violation>,
+}
+
+impl Validator<'_> {
+    fn report(&mut self, pointer: &str, at_key: bool, message: String) {
+        self.violations.push(Violation { pointer: pointer.to_string(), at_key, message });
+    }
+
+    fn validate(&mut self, value: &Value, schema: &Value, pointer: &str) {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match reference.strip_prefix('#').and_then(|p| self.root.pointer(p)) {
+                Some(target) => self.validate(value, target, pointer),
+                None => self.report(pointer, false, format!("unresolvable schema reference '{}'", reference)),
+            }
+            return;
+        }
+
+        if let Some(expected) = schema.get("type")
+            && !type_matches(value, expected)
+        {
+            self.report(
+                pointer,
+                false,
+                format!("expected {}, found {}", describe_type(expected), type_name(value)),
+            );
+            return;
+        }
+
+        if let Some(allowed) = schema.get("enum").and_then(Value::as_array)
+            && !allowed.contains(value)
+        {
+            let mut message = format!("{} is not an allowed value", value);
+            if let Some(suggestion) = value.as_str().and_then(|s| {
+                closest(s, allowed.iter().filter_map(Value::as_str))
+            }) {
+                message.push_str(&format!(" (did you mean \"{}\"?)", suggestion));
+            }
+            self.report(pointer, false, message);
+        }
+
+        if let Some(n) = value.as_f64() {
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64)
+                && n < min
+            {
+                self.report(pointer, false, format!("{} is less than the minimum of {}", value, min));
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64)
+                && n > max
+            {
+                self.report(pointer, false, format!("{} is greater than the maximum of {}", value, max));
+            }
+        }
+
+        match value {
+            Value::Object(map) => self.validate_object(map, schema, pointer),
+            Value::Array(items) => self.validate_array(items, schema, pointer),
+            _ => {}
+        }
+    }
+
+    fn validate_object(&mut self, map: &Map<String, Value>, schema: &Value, pointer: &str) {
+        let properties = schema.get("properties").and_then(Value::as_object);
+
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !map.contains_key(name) {
+                    self.report(pointer, false, format!("missing required property \"{}\"", name));
+                }
+            }
+        }
+
+        for (name, item) in map {
+            let child = format!("{}/{}", pointer, escape_pointer(name));
+            if let Some(property_schema) = properties.and_then(|p| p.get(name)) {
+                self.validate(item, property_schema, &child);
+                continue;
+            }
+            // `$schema` is always allowed at the document root for editor support
+            if pointer.is_empty() && name == "$schema" {
+                continue;
+            }
+            match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => {
+                    let mut message = format!("unknown property \"{}\"", name);
+                    if let Some(suggestion) =
+                        properties.and_then(|p| closest(name, p.keys().map(String::as_str)))
+                    {
+                        message.push_str(&format!(" (did you mean \"{}\"?)", suggestion));
+                    }
+                    self.report(&child, true, message);
+                }
+                Some(additional @ Value::Object(_)) => self.validate(item, additional, &child),
+                _ => {}
+            }
+        }
+    }
+
+    fn validate_array(&mut self, items: &[Value], schema: &Value, pointer: &str) {
+        if let Some(item_schema) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                self.validate(item, item_schema, &format!("{}/{}", pointer, index));
+            }
+        }
+
+        if schema.get("uniqueItems").and_then(Value::as_bool) == Some(true) {
+            for (index, item) in items.iter().enumerate() {
+                if items[..index].contains(item) {
+                    self.report(&format!("{}/{}", pointer, index), false, format!("duplicate item {}", item));
+                }
+            }
+        }
+    }
+}
+
+/// Check a value against a `type` keyword (string or array of strings)
+fn type_matches(value: &Value, expected: &Value) -> bool {
+    let matches = |name: &str| match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => true,
+    };
+    match expected {
+        Value::String(name) => matches(name),
+        Value::Array(names) => names.iter().filter_map(Value::as_str).any(matches),
+        _ => true,
+    }
+}
+
+fn describe_type(expected: &Value) -> String {
+    match expected {
+        Value::Array(names) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(" or "),
+        other => other.as_str().unwrap_or("value").to_string(),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Escape a key for use in a JSON pointer (RFC 6901)
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Find the closest candidate within a small edit distance
+fn closest<'c>(input: &str, candidates: impl Iterator<Item = &'c str>) -> Option<&'c str> {
+    let max_distance = (input.chars().count() / 3).max(1);
+    candidates
+        .map(|candidate| (edit_distance(input, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(row[j + 1])
+            };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+/// Source positions of every value and object key in a JSON document
+///
+/// Keyed by JSON pointer. Only used on text that has already parsed
+/// successfully, so the scanner does not need to report syntax errors.
+struct Positions {
+    values: HashMap<String, (usize, usize)>,
+    keys: HashMap<String, (usize, usize)>,
+}
+
+impl Positions {
+    fn scan(content: &str) -> Self {
+        let mut scanner = Scanner {
+            chars: content.chars().collect(),
+            index: 0,
+            line: 1,
+            column: 1,
+            positions: Positions { values: HashMap::new(), keys: HashMap::new() },
+        };
+        scanner.value(String::new());
+        scanner.positions
+    }
+
+    fn locate(&self, pointer: &str, at_key: bool) -> (usize, usize) {
+        let found = if at_key { self.keys.get(pointer) } else { None };
+        found.or_else(|| self.values.get(pointer)).copied().unwrap_or((1, 1))
+    }
+}
+
+struct Scanner {
+    chars: Vec<char>,
+    index: usize,
+    line: usize,
+    column: usize,
+    positions: Positions,
+}
+
+impl Scanner {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.index += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace() || c == '\u{feff}') {
+            self.bump();
+        }
+    }
+
+    fn value(&mut self, pointer: String) {
+        self.skip_whitespace();
+        self.positions.values.insert(pointer.clone(), (self.line, self.column));
+        match self.peek() {
+            Some('{') => self.object(&pointer),
+            Some('[') => self.array(&pointer),
+            Some('"') => {
+                self.string();
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|c| !matches!(c, ',' | '}' | ']') && !c.is_whitespace())
+                {
+                    self.bump();
+                }
+            }
+        }
+    }
+
+    fn object(&mut self, pointer: &str) {
+        self.bump(); // '{'
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('"') => {
+                    let position = (self.line, self.column);
+                    let key = self.string();
+                    let child = format!("{}/{}", pointer, escape_pointer(&key));
+                    self.positions.keys.insert(child.clone(), position);
+                    self.skip_whitespace();
+                    self.bump(); // ':'
+                    self.value(child);
+                }
+                Some(',') => {
+                    self.bump();
+                }
+                Some('}') => {
+                    self.bump();
+                    return;
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn array(&mut self, pointer: &str) {
+        self.bump(); // '['
+        let mut index = 0;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(']') => {
+                    self.bump();
+                    return;
+                }
+                Some(',') => {
+                    self.bump();
+                }
+                Some(_) => {
+                    self.value(format!("{}/{}", pointer, index));
+                    index += 1;
+                }
+                None => return,
+            }
+        }
+    }
+
+    /// Consume a string literal and return its decoded contents
+    fn string(&mut self) -> String {
+        let start = self.index;
+        self.bump(); // opening quote
+        while let Some(c) = self.bump() {
+            match c {
+                '\\' => {
+                    self.bump();
+                }
+                '"' => break,
+                _ => {}
+            }
+        }
+        let raw: String = self.chars[start..self.index].iter().collect();
+        serde_json::from_str(&raw).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(content: &str) -> Vec<SchemaError> {
+        validate_str(content, &toolset_schema(), Path::new("core.json"))
+    }
+
+    #[test]
+    fn test_valid_toolset() {
+        let errors = validate(
+            r#"{
+  "$schema": "../schemas/toolset.schema.json",
+  "tools": ["fs_read_file", "terminal"],
+  "categories": ["git"]
+}"#,
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_unknown_property_reports_key_position() {
+        let errors = validate("{\n  \"toolz\": []\n}");
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line, errors[0].column), (2, 3));
+        assert!(errors[0].message.contains("did you mean \"tools\""), "{}", errors[0]);
+        assert_eq!(errors[0].to_string(), format!("core.json:2:3: {}", errors[0].message));
+    }
+
+    #[test]
+    fn test_unknown_tool_and_category() {
+        let errors = validate("{\n  \"tools\": [\n    \"fs_read_file\",\n    \"fs_raed_file\"\n  ],\n  \"categories\": [\"gti\", 3]\n}");
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert_eq!((errors[0].line, errors[0].column), (4, 5));
+        assert!(errors[0].message.contains("did you mean \"fs_read_file\""));
+        assert_eq!(errors[1].pointer, "/categories/0");
+        assert!(errors[2].message.contains("expected string"));
+    }
+
+    #[test]
+    fn test_invalid_json_reports_position() {
+        let errors = validate("{\n  \"tools\": [,]\n}");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 2);
+        assert!(errors[0].message.starts_with("invalid JSON"));
+    }
+
+    #[test]
+    fn test_version_allowed_in_versioned_config_schemas() {
+        let schema = json!({ "type": "object", "properties": { "bind": { "type": "string" } }, "additionalProperties": false });
+        register_config_schema("test_schema_versioned.json", schema.clone());
+        register_config_schema("test_schema_plain.json", schema);
+        crate::register_migration("test_schema_versioned.json", 1, Ok);
+
+        let content = r#"{ "$schema": "x", "version": 2, "bind": "127.0.0.1" }"#;
+        let versioned = config_schema("test_schema_versioned.json").unwrap();
+        assert!(validate_str(content, &versioned, Path::new("v.json")).is_empty());
+        let errors = validate_str(r#"{ "version": "2" }"#, &versioned, Path::new("v.json"));
+        assert!(errors[0].message.contains("expected integer"), "{:?}", errors);
+
+        let plain = config_schema("test_schema_plain.json").unwrap();
+        let errors = validate_str(content, &plain, Path::new("p.json"));
+        assert!(errors[0].message.contains("unknown property \"version\""), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_add_schema_reference_preserves_formatting() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("core.json");
+        fs::write(&path, "{\n  \"tools\": [\"terminal\"]\n}\n").unwrap();
+
+        add_schema_reference(&path, "../schemas/toolset.schema.json").unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            content,
+            "{\n  \"$schema\": \"../schemas/toolset.schema.json\",\n  \"tools\": [\"terminal\"]\n}\n"
+        );
+
+        // Idempotent
+        add_schema_reference(&path, "../schemas/toolset.schema.json").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+    }
+}
@@ -0,0 +1,490 @@
+//! Encrypted secrets store
+//!
+//! Secrets (GitHub tokens, database connection strings, API keys) are kept out
+//! of plain `config/*.json` files and stored individually under
+//! `data_dir()/secrets/`:
+//!
+//! ```text
+//! ~/.config/kodegen/data/secrets/
+//! ├── .key                 # 32-byte ChaCha20-Poly1305 key (0600)
+//! ├── github_token.secret  # nonce + ciphertext (0600)
+//! └── database_url.secret
+//! ```
+//!
+//! Config values reference secrets by name as `${secret:github_token}`; the
+//! reference is expanded by the interpolation pass when the config file is loaded.
+//!
+//! A git-local layer (`.kodegen/local/secrets/`) is also searched.
+//! `init_structure()` git-ignores that directory, but nothing guarantees the
+//! rule is present in a given clone (init never ran there, or the rule lives
+//! in another clone's `.git/info/exclude`), so secrets found there are refused
+//! unless explicitly allowed via [`SecretStore::allow_local`] or
+//! `KODEGEN_ALLOW_LOCAL_SECRETS=1`.
+//!
+//! Older builds used `.kodegen/secrets/`, which is committed with the shared
+//! layout. Secrets there are never read: looking one up fails with an error
+//! asking to move it, and opening the store warns while the directory exists.
+
+use anyhow::{anyhow, bail, Context, Result};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Name of the key file inside the user secrets directory
+const KEY_FILE: &str = ".key";
+
+/// Extension used for individual secret files
+const SECRET_EXTENSION: &str = "secret";
+
+/// Magic header identifying the on-disk secret format (version 1)
+const SECRET_MAGIC: &[u8; 4] = b"KGS1";
+
+/// Nonce length for ChaCha20-Poly1305
+const NONCE_LEN: usize = 12;
+
+/// Key length for ChaCha20-Poly1305
+const KEY_LEN: usize = 32;
+
+/// Check if the KODEGEN_ALLOW_LOCAL_SECRETS override is enabled
+fn is_local_secrets_allowed() -> bool {
+    std::env::var("KODEGEN_ALLOW_LOCAL_SECRETS")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Encrypted, name-addressable secret storage
+///
+/// Secrets are resolved with local > user precedence, mirroring
+/// [`resolve_config_file()`](crate::KodegenConfig::resolve_config_file), except
+/// that the local layer is refused unless explicitly allowed.
+#[derive(Debug, Clone)]
+pub struct SecretStore {
+    user_dir: PathBuf,
+    local_dir: Option<PathBuf>,
+    /// Former local layer (`.kodegen/secrets/`), checked only to report stale secrets
+    shared_dir: Option<PathBuf>,
+    allow_local: bool,
+}
+
+impl SecretStore {
+    /// Open the default store
+    ///
+    /// **User layer**: `data_dir()/secrets/`
+    /// **Local layer**: `${git_root}/.kodegen/local/secrets/` (if in a git repository)
+    ///
+    /// The local layer lives in the private part of `.kodegen/` so it stays
+    /// ignored with the shared layout. Secrets left in the former
+    /// `.kodegen/secrets/` location are reported, not read.
+    pub fn open() -> Result<Self> {
+        let user_dir = crate::KodegenConfig::data_dir()?.join("secrets");
+        let local_dir = crate::KodegenConfig::local_private_dir()
+            .ok()
+            .map(|dir| dir.join("secrets"));
+        let mut store = Self::with_dirs(user_dir, local_dir);
+
+        store.shared_dir = crate::KodegenConfig::local_config_dir().ok().map(|dir| dir.join("secrets"));
+        if let Some(shared_dir) = &store.shared_dir
+            && shared_dir.is_dir()
+        {
+            log::warn!(
+                "Security: {} is no longer read and is committed with the shared layout; \
+                 move its secrets to {} or {}",
+                shared_dir.display(),
+                store.local_dir.as_deref().unwrap_or(&store.user_dir).display(),
+                store.user_dir.display()
+            );
+        }
+        Ok(store)
+    }
+
+    /// Create a store rooted at explicit directories
+    ///
+    /// The key file always lives in `user_dir`.
+    pub fn with_dirs(user_dir: PathBuf, local_dir: Option<PathBuf>) -> Self {
+        Self {
+            user_dir,
+            local_dir,
+            shared_dir: None,
+            allow_local: is_local_secrets_allowed(),
+        }
+    }
+
+    /// Allow (or refuse) secrets found in the git-local layer
+    pub fn allow_local(mut self, allow: bool) -> Self {
+        self.allow_local = allow;
+        self
+    }
+
+    /// User secrets directory (`data_dir()/secrets/` for the default store)
+    pub fn user_dir(&self) -> &Path {
+        &self.user_dir
+    }
+
+    /// Store a secret in the user layer, replacing any existing value
+    ///
+    /// Creates the secrets directory (0700) and key file (0600) on first use.
+    /// Returns the path of the written secret file.
+    pub fn set(&self, name: &str, value: &str) -> Result<PathBuf> {
+        crate::validation::validate_name(name).map_err(|e| anyhow!("Invalid secret name: {}", e))?;
+
+        let cipher = self.cipher(true)?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: value.as_bytes(), aad: name.as_bytes() })
+            .map_err(|_| anyhow!("Failed to encrypt secret '{}'", name))?;
+
+        let mut contents = Vec::with_capacity(SECRET_MAGIC.len() + NONCE_LEN + ciphertext.len());
+        contents.extend_from_slice(SECRET_MAGIC);
+        contents.extend_from_slice(&nonce);
+        contents.extend_from_slice(&ciphertext);
+
+        let path = self.user_dir.join(secret_filename(name));
+        write_private(&self.user_dir, &path, &contents)?;
+        Ok(path)
+    }
+
+    /// Decrypt a secret by name
+    ///
+    /// **Search order**:
+    /// 1. `${git_root}/.kodegen/local/secrets/{name}.secret` (refused unless allowed)
+    /// 2. `data_dir()/secrets/{name}.secret`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the secret is missing, found only in a refused local
+    /// layer, has permissions readable by other users, or fails to decrypt.
+    pub fn get(&self, name: &str) -> Result<String> {
+        crate::validation::validate_name(name).map_err(|e| anyhow!("Invalid secret name: {}", e))?;
+
+        let filename = secret_filename(name);
+        let mut searched_paths = Vec::new();
+
+        if let Some(local_dir) = &self.local_dir {
+            searched_paths.push(local_dir.join(&filename).display().to_string());
+            if let Some(path) = crate::try_resolve_in_dir(local_dir, "", &filename) {
+                if !self.allow_local {
+                    log::warn!(
+                        "Security: Refusing secret '{}' from git-local layer: {}",
+                        name,
+                        path.display()
+                    );
+                    bail!(
+                        "Secret '{}' found in git-local layer ({}), which may be committed. \
+                         Move it to {} or set KODEGEN_ALLOW_LOCAL_SECRETS=1",
+                        name,
+                        path.display(),
+                        self.user_dir.display()
+                    );
+                }
+                return self.decrypt_file(name, &path);
+            }
+        }
+
+        searched_paths.push(self.user_dir.join(&filename).display().to_string());
+        if let Some(path) = crate::try_resolve_in_dir(&self.user_dir, "", &filename) {
+            return self.decrypt_file(name, &path);
+        }
+
+        if let Some(path) = self.shared_dir.as_deref().and_then(|dir| crate::try_resolve_in_dir(dir, "", &filename)) {
+            bail!(
+                "Secret '{}' is in the former git-local location {}, which is no longer read \
+                 and may be committed. Move it to {} (or {})",
+                name,
+                path.display(),
+                self.local_dir.as_deref().unwrap_or(&self.user_dir).display(),
+                self.user_dir.display()
+            );
+        }
+
+        Err(anyhow!(
+            "Secret '{}' not found. Searched:\n  {}",
+            name,
+            searched_paths.join("\n  ")
+        ))
+    }
+
+    /// Remove a secret from the user layer
+    ///
+    /// Returns `true` if a secret was removed, `false` if it did not exist.
+    pub fn remove(&self, name: &str) -> Result<bool> {
+        crate::validation::validate_name(name).map_err(|e| anyhow!("Invalid secret name: {}", e))?;
+
+        let path = self.user_dir.join(secret_filename(name));
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("Failed to remove secret: {}", path.display())),
+        }
+    }
+
+    /// List secret names stored in the user layer (sorted)
+    pub fn list(&self) -> Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.user_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", self.user_dir.display())),
+        };
+
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == SECRET_EXTENSION))
+            .filter_map(|path| path.file_stem().and_then(|s| s.to_str()).map(str::to_string))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Decrypt a single secret file
+    fn decrypt_file(&self, name: &str, path: &Path) -> Result<String> {
+        check_private_permissions(path)?;
+
+        let contents = fs::read(path).with_context(|| format!("Failed to read secret: {}", path.display()))?;
+        if contents.len() < SECRET_MAGIC.len() + NONCE_LEN || &contents[..SECRET_MAGIC.len()] != SECRET_MAGIC {
+            bail!("Secret file is corrupted or has an unknown format: {}", path.display());
+        }
+
+        let (nonce, ciphertext) = contents[SECRET_MAGIC.len()..].split_at(NONCE_LEN);
+        let cipher = self.cipher(false)?;
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: name.as_bytes() })
+            .map_err(|_| {
+                anyhow!(
+                    "Failed to decrypt secret '{}' ({}). The key file may have changed",
+                    name,
+                    path.display()
+                )
+            })?;
+
+        String::from_utf8(plaintext).map_err(|_| anyhow!("Secret '{}' is not valid UTF-8", name))
+    }
+
+    /// Load the cipher from the key file, creating the key if requested
+    ///
+    /// The key is created without overwriting, so processes storing their
+    /// first secret at the same time all end up with the same key.
+    fn cipher(&self, create: bool) -> Result<ChaCha20Poly1305> {
+        let key_path = self.user_dir.join(KEY_FILE);
+
+        match fs::read(&key_path) {
+            Ok(bytes) => cipher_from_key_file(&key_path, bytes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && create => {
+                let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+                if create_private(&self.user_dir, &key_path, &key)? {
+                    log::info!("Created secrets key file: {}", key_path.display());
+                    return Ok(ChaCha20Poly1305::new(&key));
+                }
+                // Another process created it first: use its key
+                let bytes = fs::read(&key_path)
+                    .with_context(|| format!("Failed to read secrets key: {}", key_path.display()))?;
+                cipher_from_key_file(&key_path, bytes)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(anyhow!(
+                "Secrets key file not found: {}",
+                key_path.display()
+            )),
+            Err(e) => Err(e).with_context(|| format!("Failed to read secrets key: {}", key_path.display())),
+        }
+    }
+}
+
+fn cipher_from_key_file(key_path: &Path, bytes: Vec<u8>) -> Result<ChaCha20Poly1305> {
+    check_private_permissions(key_path)?;
+    if bytes.len() != KEY_LEN {
+        bail!(
+            "Secrets key file has invalid length ({} bytes, expected {}): {}",
+            bytes.len(),
+            KEY_LEN,
+            key_path.display()
+        );
+    }
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&bytes)))
+}
+
+/// Filename for a secret name
+fn secret_filename(name: &str) -> String {
+    format!("{}.{}", name, SECRET_EXTENSION)
+}
+
+/// Atomically write a file readable only by the current user
+///
+/// Uses the same temp-file + rename pattern as `.gitignore` updates so a crash
+/// never leaves a partially written key or secret behind. Also used for the
+/// infrastructure header signing key.
+pub(crate) fn write_private(dir: &Path, path: &Path, contents: &[u8]) -> Result<()> {
+    private_temp_file(dir, contents)?
+        .persist(path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Atomically create a file readable only by the current user, unless it exists
+///
+/// Returns `false` without touching the file if another process created it
+/// first, so concurrent creators agree on the winner's contents.
+pub(crate) fn create_private(dir: &Path, path: &Path, contents: &[u8]) -> Result<bool> {
+    match private_temp_file(dir, contents)?.persist_noclobber(path) {
+        Ok(_) => Ok(true),
+        Err(e) if e.error.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+        Err(e) => Err(e.error).with_context(|| format!("Failed to create {}", path.display())),
+    }
+}
+
+/// Owner-only temp file in `dir` holding `contents`, synced to disk
+fn private_temp_file(dir: &Path, contents: &[u8]) -> Result<NamedTempFile> {
+    create_private_dir(dir)?;
+
+    let mut temp_file = NamedTempFile::new_in(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        temp_file.as_file().set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    temp_file.write_all(contents)?;
+    temp_file.as_file().sync_all()?;
+    Ok(temp_file)
+}
+
+/// Create a directory (and parents) with owner-only access
+pub(crate) fn create_private_dir(dir: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)
+            .with_context(|| format!("Failed to create private directory: {}", dir.display()))
+    }
+    #[cfg(not(unix))]
+    {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create private directory: {}", dir.display()))
+    }
+}
+
+/// Refuse key and secret files that other users can read
+#[cfg(unix)]
+pub(crate) fn check_private_permissions(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path)?.permissions().mode();
+    if mode & 0o077 != 0 {
+        log::warn!(
+            "Security: Refusing {} - permissions {:o} allow access by other users",
+            path.display(),
+            mode & 0o777
+        );
+        bail!(
+            "Permissions {:o} for {} are too open. Run: chmod 600 {}",
+            mode & 0o777,
+            path.display(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn check_private_permissions(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(dir: &Path) -> SecretStore {
+        SecretStore::with_dirs(dir.join("user"), Some(dir.join("local"))).allow_local(false)
+    }
+
+    #[test]
+    fn test_set_get_roundtrip() {
+        let temp = tempfile::tempdir().unwrap();
+        let store = store(temp.path());
+
+        let path = store.set("github_token", "ghp_example").unwrap();
+        assert_eq!(store.get("github_token").unwrap(), "ghp_example");
+
+        // Ciphertext must not contain the plaintext
+        let raw = fs::read(&path).unwrap();
+        assert!(!raw.windows(11).any(|w| w == b"ghp_example"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+            let key = store.user_dir().join(KEY_FILE);
+            assert_eq!(fs::metadata(key).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_concurrent_key_creation_agrees() {
+        let temp = tempfile::tempdir().unwrap();
+        let store = SecretStore::with_dirs(temp.path().join("secrets"), None);
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let store = &store;
+                scope.spawn(move || store.set(&format!("token_{}", i), &format!("value {}", i)).unwrap());
+            }
+        });
+        // Every secret was encrypted with the key that ended up on disk
+        for i in 0..8 {
+            assert_eq!(store.get(&format!("token_{}", i)).unwrap(), format!("value {}", i));
+        }
+    }
+
+    #[test]
+    fn test_renamed_secret_fails_to_decrypt() {
+        let temp = tempfile::tempdir().unwrap();
+        let store = store(temp.path());
+
+        let path = store.set("a", "value").unwrap();
+        fs::rename(&path, store.user_dir().join("b.secret")).unwrap();
+        assert!(store.get("b").is_err());
+    }
+
+    #[test]
+    fn test_local_layer_refused_unless_allowed() {
+        let temp = tempfile::tempdir().unwrap();
+        let user = store(temp.path());
+        user.set("token", "from-local").unwrap();
+
+        // Simulate a secret that ended up in the git-local layer
+        let local_dir = temp.path().join("local");
+        fs::create_dir_all(&local_dir).unwrap();
+        fs::rename(user.user_dir().join("token.secret"), local_dir.join("token.secret")).unwrap();
+
+        let err = user.get("token").unwrap_err().to_string();
+        assert!(err.contains("git-local layer"), "{}", err);
+        assert_eq!(user.allow_local(true).get("token").unwrap(), "from-local");
+    }
+
+    #[test]
+    fn test_stale_shared_secret_reported_not_read() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut store = store(temp.path());
+        store.shared_dir = Some(temp.path().join("shared"));
+        store.set("token", "old").unwrap();
+        fs::create_dir_all(temp.path().join("shared")).unwrap();
+        fs::rename(store.user_dir().join("token.secret"), temp.path().join("shared/token.secret")).unwrap();
+
+        let err = store.allow_local(true).get("token").unwrap_err().to_string();
+        assert!(err.contains("no longer read"), "{}", err);
+    }
+
+    #[test]
+    fn test_invalid_and_missing_names() {
+        let temp = tempfile::tempdir().unwrap();
+        let store = store(temp.path());
+
+        assert!(store.set("../escape", "x").is_err());
+        assert!(store.get("missing").unwrap_err().to_string().contains("not found"));
+        assert!(!store.remove("missing").unwrap());
+        assert!(store.list().unwrap().is_empty());
+    }
+}
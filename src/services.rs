@@ -0,0 +1,301 @@
+//! Service endpoint discovery
+//!
+//! A service is reached over a loopback TCP port or a Unix domain socket
+//! ([`Endpoint`]). A service that binds an ephemeral port or a socket
+//! publishes it as a [`StateRecord`] at `state_dir()/{category}/server.json`.
+//! The stdio router resolves the endpoint of a category in this order:
+//!
+//! 1. The live published record of the category's server
+//! 2. The [`PortMap`] selection: the category's socket path if its
+//!    [`Transport`] is `unix`, else its port (`ports.json` /
+//!    `KODEGEN_PORT_*` overrides)
+//! 3. The static default from [`CATEGORY_PORTS`](crate::CATEGORY_PORTS)
+//!
+//! Records of crashed services are ignored, so a stale file never routes
+//! traffic to a dead port.
+//!
+//! **Socket paths**: `$XDG_RUNTIME_DIR/kodegen/{category}.sock` when
+//! `XDG_RUNTIME_DIR` is set (`kodegen-{instance}/` for a named instance),
+//! else `state_dir()/sockets/{category}.sock`.
+//! Paths too long for `sun_path` (107 bytes on Linux, 103 on macOS/BSD) move
+//! to a short `kodegen-{hash}/` directory in the system temp dir. Socket
+//! directories are created owner-only and refused if another user owns them.
+
+use anyhow::{anyhow, bail, Context, Result};
+use sha2::{Digest, Sha256};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+use crate::constants::Category;
+use crate::ports::{PortMap, Transport};
+use crate::state::{StateRecord, StateRegistry};
+
+/// Name of the state record a category's server publishes
+pub const SERVICE_RECORD_NAME: &str = "server";
+
+/// Subdirectory of `state_dir()` holding sockets when `XDG_RUNTIME_DIR` is unset
+pub const SOCKETS_DIR: &str = "sockets";
+
+/// Longest socket path `bind()` accepts (`sun_path` minus the NUL terminator)
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+))]
+const MAX_SOCKET_PATH: usize = 103;
+
+/// Longest socket path `bind()` accepts (`sun_path` minus the NUL terminator)
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+)))]
+const MAX_SOCKET_PATH: usize = 107;
+
+/// Where a service listens
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    /// Loopback TCP port
+    Tcp(u16),
+    /// Unix domain socket
+    Unix(PathBuf),
+}
+
+impl Endpoint {
+    /// Loopback socket address of a TCP endpoint
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Self::Tcp(port) => Some(SocketAddr::from((Ipv4Addr::LOCALHOST, *port))),
+            Self::Unix(_) => None,
+        }
+    }
+
+    /// Socket path of a Unix endpoint
+    pub fn socket_path(&self) -> Option<&Path> {
+        match self {
+            Self::Tcp(_) => None,
+            Self::Unix(path) => Some(path),
+        }
+    }
+
+    /// Transport of this endpoint
+    pub fn transport(&self) -> Transport {
+        match self {
+            Self::Tcp(_) => Transport::Tcp,
+            Self::Unix(_) => Transport::Unix,
+        }
+    }
+}
+
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(port) => write!(f, "{}:{}", Ipv4Addr::LOCALHOST, port),
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Publish the endpoint the current process serves `category` on
+///
+/// The record is owned by the current process and ignored once it exits.
+pub(crate) fn publish(registry: &StateRegistry, category: &Category, endpoint: &Endpoint, version: &str) -> Result<PathBuf> {
+    let record = StateRecord::current(backend(category)?.name, SERVICE_RECORD_NAME, version);
+    let record = match endpoint {
+        Endpoint::Tcp(port) => record.with_port(*port),
+        Endpoint::Unix(path) => record.with_socket(path.clone()),
+    };
+    registry.register(&record)
+}
+
+/// Remove the current process's published record for `category`
+pub(crate) fn unpublish(registry: &StateRegistry, category: &Category) -> Result<bool> {
+    registry.unregister(backend(category)?.name, SERVICE_RECORD_NAME)
+}
+
+/// Resolve the endpoint serving `category`
+pub(crate) fn resolve(
+    registry: &StateRegistry,
+    ports: &PortMap,
+    socket_dir: &Path,
+    category: &Category,
+) -> Result<Endpoint> {
+    let backend = backend(category)?;
+    if let Some(record) = registry.get(backend.name, SERVICE_RECORD_NAME)? {
+        if let Some(socket) = record.socket {
+            return Ok(Endpoint::Unix(socket));
+        }
+        if let Some(port) = record.port {
+            return Ok(Endpoint::Tcp(port));
+        }
+    }
+
+    configured(ports, socket_dir, category)
+}
+
+/// Endpoint the [`PortMap`] selects for `category`, ignoring published records
+pub(crate) fn configured(ports: &PortMap, socket_dir: &Path, category: &Category) -> Result<Endpoint> {
+    let backend = backend(category)?;
+    match ports.transport(backend) {
+        Transport::Unix => Ok(Endpoint::Unix(socket_path(socket_dir, backend)?)),
+        Transport::Tcp => ports
+            .port(backend)
+            .map(Endpoint::Tcp)
+            .ok_or_else(|| anyhow!("No port known for category '{}'", category)),
+    }
+}
+
+/// Preferred directory for service sockets
+///
+/// `state_dir` is already instance-specific; the runtime dir is shared, so
+/// named instances get their own subdirectory there.
+pub(crate) fn socket_dir(state_dir: &Path, runtime_dir: Option<PathBuf>, instance: Option<&str>) -> PathBuf {
+    match (runtime_dir.filter(|dir| dir.is_absolute()), instance) {
+        (Some(runtime_dir), None) => runtime_dir.join("kodegen"),
+        (Some(runtime_dir), Some(instance)) => runtime_dir.join(format!("kodegen-{}", instance)),
+        (None, _) => state_dir.join(SOCKETS_DIR),
+    }
+}
+
+/// Socket path for `category` in `dir`, shortened to fit `sun_path`
+pub(crate) fn socket_path(dir: &Path, category: &Category) -> Result<PathBuf> {
+    let filename = format!("{}.sock", backend(category)?.name);
+    let path = dir.join(&filename);
+    if path.as_os_str().len() <= MAX_SOCKET_PATH {
+        return Ok(path);
+    }
+
+    // Stable per preferred directory, so every process derives the same path
+    let digest = Sha256::digest(dir.as_os_str().as_encoded_bytes());
+    let hash: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    let short = std::env::temp_dir().join(format!("kodegen-{}", hash)).join(&filename);
+    if short.as_os_str().len() > MAX_SOCKET_PATH {
+        bail!(
+            "Socket path for {} exceeds {} bytes even in the temp dir: {}",
+            category,
+            MAX_SOCKET_PATH,
+            short.display()
+        );
+    }
+    Ok(short)
+}
+
+/// Create a socket's directory owner-only, refusing one another user controls
+pub(crate) fn prepare_socket_dir(socket: &Path) -> Result<()> {
+    let dir = socket.parent().ok_or_else(|| anyhow!("Invalid socket path: {}", socket.display()))?;
+    crate::secrets::create_private_dir(dir)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = std::fs::symlink_metadata(dir)
+            .with_context(|| format!("Failed to inspect socket directory: {}", dir.display()))?;
+        if !metadata.is_dir() || metadata.uid() != nix::unistd::getuid().as_raw() || metadata.mode() & 0o077 != 0 {
+            bail!(
+                "Refusing socket directory {}: it must be a directory owned by the current user with mode 0700",
+                dir.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn backend(category: &Category) -> Result<&'static Category> {
+    category.backend().ok_or_else(|| anyhow!("Unknown category '{}'", category))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{CATEGORY_CANDLE_AGENT, CATEGORY_GIT, CATEGORY_MEMORY, PORT_GIT};
+
+    #[test]
+    fn test_published_endpoint_wins_over_static() {
+        let temp = tempfile::tempdir().unwrap();
+        let registry = StateRegistry::at(temp.path().to_path_buf());
+        let ports = PortMap::defaults();
+        let sockets = temp.path().join(SOCKETS_DIR);
+
+        assert_eq!(resolve(&registry, &ports, &sockets, CATEGORY_GIT).unwrap(), Endpoint::Tcp(PORT_GIT));
+
+        publish(&registry, CATEGORY_GIT, &Endpoint::Tcp(54321), "1.0.0").unwrap();
+        let endpoint = resolve(&registry, &ports, &sockets, CATEGORY_GIT).unwrap();
+        assert_eq!(endpoint.socket_addr(), Some(SocketAddr::from((Ipv4Addr::LOCALHOST, 54321))));
+
+        // Memory tools route to the candle-agent server's record
+        let socket = sockets.join("candle_agent.sock");
+        publish(&registry, CATEGORY_MEMORY, &Endpoint::Unix(socket.clone()), "1.0.0").unwrap();
+        assert!(registry.record_path(CATEGORY_CANDLE_AGENT.name, SERVICE_RECORD_NAME).unwrap().exists());
+        assert_eq!(resolve(&registry, &ports, &sockets, CATEGORY_CANDLE_AGENT).unwrap(), Endpoint::Unix(socket));
+
+        assert!(unpublish(&registry, CATEGORY_GIT).unwrap());
+        assert_eq!(resolve(&registry, &ports, &sockets, CATEGORY_GIT).unwrap(), Endpoint::Tcp(PORT_GIT));
+
+        let mut unix = PortMap::defaults();
+        unix.set_transport(CATEGORY_GIT, Transport::Unix).unwrap();
+        assert_eq!(
+            resolve(&registry, &unix, &sockets, CATEGORY_GIT).unwrap(),
+            Endpoint::Unix(sockets.join("git.sock"))
+        );
+    }
+
+    #[test]
+    fn test_record_of_dead_service_ignored() {
+        let temp = tempfile::tempdir().unwrap();
+        let registry = StateRegistry::at(temp.path().to_path_buf());
+
+        let mut child = std::process::Command::new(if cfg!(windows) { "cmd" } else { "true" })
+            .args(if cfg!(windows) { &["/C", "exit"][..] } else { &[][..] })
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+
+        let stale = StateRecord { pid, ..StateRecord::current("git", SERVICE_RECORD_NAME, "1.0.0").with_port(54321) };
+        registry.register(&stale).unwrap();
+        let endpoint = resolve(&registry, &PortMap::defaults(), temp.path(), CATEGORY_GIT).unwrap();
+        assert_eq!(endpoint, Endpoint::Tcp(PORT_GIT));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_socket_paths() {
+        let state = Path::new("/home/user/.config/kodegen/state");
+        let run = || Some(PathBuf::from("/run/user/1000"));
+        assert_eq!(socket_dir(state, None, None), state.join(SOCKETS_DIR));
+        assert_eq!(socket_dir(state, Some(PathBuf::from("relative")), None), state.join(SOCKETS_DIR));
+        assert_eq!(socket_dir(state, run(), None), Path::new("/run/user/1000/kodegen"));
+        assert_eq!(socket_dir(state, run(), Some("dev")), Path::new("/run/user/1000/kodegen-dev"));
+
+        let runtime = Path::new("/run/user/1000/kodegen");
+        assert_eq!(socket_path(runtime, CATEGORY_MEMORY).unwrap(), runtime.join("candle_agent.sock"));
+
+        let deep = Path::new("/home").join("a".repeat(120));
+        let short = socket_path(&deep, CATEGORY_GIT).unwrap();
+        assert!(short.as_os_str().len() <= MAX_SOCKET_PATH, "{}", short.display());
+        assert!(short.starts_with(std::env::temp_dir()));
+        assert_eq!(short, socket_path(&deep, CATEGORY_GIT).unwrap(), "must be stable");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_socket_dir_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::tempdir().unwrap();
+        let socket = temp.path().join("sockets/git.sock");
+        prepare_socket_dir(&socket).unwrap();
+        let mode = std::fs::metadata(temp.path().join("sockets")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        std::fs::set_permissions(temp.path().join("sockets"), std::fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(prepare_socket_dir(&socket).is_err());
+    }
+}
@@ -0,0 +1,309 @@
+//! HMAC-signed infrastructure headers
+//!
+//! Backends listen on localhost ports any local process can reach, so the
+//! `X_KODEGEN_*` context headers are signed by the stdio server and verified
+//! by the backend. Both sides run as the same user and share a 32-byte key in
+//! `state_dir()/infra.key` (mode 0600, created on first use).
+//!
+//! The signature is `hex(HMAC-SHA256(key, canonical))` over:
+//!
+//! ```text
+//! kodegen-infra-v1
+//! {timestamp}
+//! {nonce}
+//! {x-kodegen-connection-id value}
+//! {x-kodegen-pwd value}
+//! {x-kodegen-gitroot value, or empty}
+//! ```
+//!
+//! Verification rejects timestamps outside [`HeaderSigner::max_skew`] of the
+//! local clock and nonces already seen within that window (replay). Repeated
+//! `x-kodegen-*` headers are rejected, so the returned context is always built
+//! from exactly the values that were authenticated.
+
+use anyhow::{anyhow, bail, Context, Result};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::headers::InfraContext;
+use crate::{
+    X_KODEGEN_CONNECTION_ID, X_KODEGEN_GITROOT, X_KODEGEN_NONCE, X_KODEGEN_PWD, X_KODEGEN_SIGNATURE,
+    X_KODEGEN_TIMESTAMP,
+};
+
+/// Signing key file in `state_dir()`
+pub const INFRA_KEY_FILE: &str = "infra.key";
+
+/// Domain separation prefix of the signed message
+const SIGNATURE_VERSION: &str = "kodegen-infra-v1";
+
+/// Key length for HMAC-SHA256
+const KEY_LEN: usize = 32;
+
+/// Nonce length in bytes (hex-encoded in the header)
+const NONCE_LEN: usize = 16;
+
+/// Prefix shared by all infrastructure header names
+const KODEGEN_HEADER_PREFIX: &str = "x-kodegen-";
+
+/// Default accepted clock difference between signer and verifier
+const DEFAULT_MAX_SKEW: Duration = Duration::from_secs(60);
+
+/// Signs and verifies infrastructure headers with a shared per-user key
+#[derive(Debug)]
+pub struct HeaderSigner {
+    key: [u8; KEY_LEN],
+    max_skew: Duration,
+    /// Nonces accepted within the skew window, with their timestamps
+    seen: parking_lot::Mutex<HashMap<String, u64>>,
+}
+
+impl HeaderSigner {
+    /// Load the key from `state_dir()`, creating it on first use
+    pub fn open() -> Result<Self> {
+        Self::open_in(&crate::KodegenConfig::state_dir()?)
+    }
+
+    /// Load the key from `{dir}/infra.key`, creating it on first use
+    ///
+    /// The key is created without overwriting, so processes starting together
+    /// all end up with the key of whichever created it first.
+    pub fn open_in(dir: &Path) -> Result<Self> {
+        let key_path = dir.join(INFRA_KEY_FILE);
+        match std::fs::read(&key_path) {
+            Ok(bytes) => Self::from_key_file(&key_path, bytes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut key = [0u8; KEY_LEN];
+                OsRng.fill_bytes(&mut key);
+                if crate::secrets::create_private(dir, &key_path, &key)? {
+                    log::info!("Created infrastructure signing key: {}", key_path.display());
+                    return Ok(Self::from_key(key));
+                }
+                // Another process created it first: use its key
+                let bytes = std::fs::read(&key_path)
+                    .with_context(|| format!("Failed to read infrastructure key: {}", key_path.display()))?;
+                Self::from_key_file(&key_path, bytes)
+            }
+            Err(e) => Err(e).with_context(|| format!("Failed to read infrastructure key: {}", key_path.display())),
+        }
+    }
+
+    fn from_key_file(key_path: &Path, bytes: Vec<u8>) -> Result<Self> {
+        crate::secrets::check_private_permissions(key_path)?;
+        let key: [u8; KEY_LEN] = bytes.try_into().map_err(|bytes: Vec<u8>| {
+            anyhow!(
+                "Infrastructure key file has invalid length ({} bytes, expected {}): {}",
+                bytes.len(),
+                KEY_LEN,
+                key_path.display()
+            )
+        })?;
+        Ok(Self::from_key(key))
+    }
+
+    /// Create a signer from raw key bytes
+    pub fn from_key(key: [u8; KEY_LEN]) -> Self {
+        Self { key, max_skew: DEFAULT_MAX_SKEW, seen: parking_lot::Mutex::new(HashMap::new()) }
+    }
+
+    /// Set the accepted clock difference (default 60 seconds)
+    pub fn max_skew(mut self, max_skew: Duration) -> Self {
+        self.max_skew = max_skew;
+        self
+    }
+
+    /// Context headers plus timestamp, nonce and signature headers
+    pub fn sign(&self, context: &InfraContext) -> Vec<(&'static str, String)> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        self.sign_with(context, unix_now(), &hex(&nonce))
+    }
+
+    fn sign_with(&self, context: &InfraContext, timestamp: u64, nonce: &str) -> Vec<(&'static str, String)> {
+        let mut headers = context.to_headers();
+        let signature = self.signature(&headers, &timestamp.to_string(), nonce);
+        headers.push((X_KODEGEN_TIMESTAMP, timestamp.to_string()));
+        headers.push((X_KODEGEN_NONCE, nonce.to_string()));
+        headers.push((X_KODEGEN_SIGNATURE, signature));
+        headers
+    }
+
+    /// Verify signed headers and return the validated context
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a header is missing or repeated, the signature does
+    /// not match, the timestamp is outside the skew window, the nonce was
+    /// already used, or the context is invalid.
+    pub fn verify<'a, I>(&self, headers: I) -> Result<InfraContext>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let headers: Vec<(&str, &str)> = headers.into_iter().map(|(n, v)| (n, v.trim())).collect();
+        for (i, (name, _)) in headers.iter().enumerate() {
+            if name.get(..KODEGEN_HEADER_PREFIX.len()).is_some_and(|p| p.eq_ignore_ascii_case(KODEGEN_HEADER_PREFIX))
+                && headers[..i].iter().any(|(earlier, _)| earlier.eq_ignore_ascii_case(name))
+            {
+                bail!("Repeated {} header", name.to_ascii_lowercase());
+            }
+        }
+        let get = |name: &str| headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| *v);
+        let require = |name: &str| get(name).ok_or_else(|| anyhow!("Missing {} header", name));
+
+        let timestamp = require(X_KODEGEN_TIMESTAMP)?;
+        let nonce = require(X_KODEGEN_NONCE)?;
+        let signature = require(X_KODEGEN_SIGNATURE)?;
+
+        let signed: Vec<(&str, String)> = [X_KODEGEN_CONNECTION_ID, X_KODEGEN_PWD, X_KODEGEN_GITROOT]
+            .into_iter()
+            .filter_map(|name| get(name).map(|v| (name, v.to_string())))
+            .collect();
+        let mac = self.mac(&signed, timestamp, nonce);
+        let expected = unhex(signature).ok_or_else(|| anyhow!("Malformed {} header", X_KODEGEN_SIGNATURE))?;
+        mac.verify_slice(&expected)
+            .map_err(|_| anyhow!("Invalid {}: headers were not signed by this user", X_KODEGEN_SIGNATURE))?;
+
+        // Only authenticated values are checked for freshness
+        let timestamp: u64 = timestamp
+            .parse()
+            .map_err(|_| anyhow!("Malformed {} header", X_KODEGEN_TIMESTAMP))?;
+        let now = unix_now();
+        if now.abs_diff(timestamp) > self.max_skew.as_secs() {
+            bail!("Expired {}: signed {}s from now", X_KODEGEN_TIMESTAMP, timestamp as i64 - now as i64);
+        }
+        {
+            let mut seen = self.seen.lock();
+            let window = self.max_skew.as_secs();
+            seen.retain(|_, &mut ts| now.abs_diff(ts) <= window);
+            if seen.insert(nonce.to_string(), timestamp).is_some() {
+                bail!("Replayed {}: nonce already used", X_KODEGEN_NONCE);
+            }
+        }
+
+        InfraContext::from_headers(signed.iter().map(|(n, v)| (*n, v.as_str())))
+    }
+
+    fn signature(&self, headers: &[(&str, String)], timestamp: &str, nonce: &str) -> String {
+        hex(&self.mac(headers, timestamp, nonce).finalize().into_bytes())
+    }
+
+    fn mac(&self, headers: &[(&str, String)], timestamp: &str, nonce: &str) -> Hmac<Sha256> {
+        let value = |name: &str| headers.iter().find(|(n, _)| *n == name).map_or("", |(_, v)| v.as_str());
+        let message = [
+            SIGNATURE_VERSION,
+            timestamp,
+            nonce,
+            value(X_KODEGEN_CONNECTION_ID),
+            value(X_KODEGEN_PWD),
+            value(X_KODEGEN_GITROOT),
+        ]
+        .join("\n");
+
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(message.as_bytes());
+        mac
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(value: &str) -> Option<Vec<u8>> {
+    if value.len() % 2 != 0 {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| value.get(i..i + 2).and_then(|h| u8::from_str_radix(h, 16).ok()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn context() -> InfraContext {
+        let root = if cfg!(windows) { "C:/repo" } else { "/repo" };
+        InfraContext::new("conn-1", PathBuf::from(root).join("src"), Some(PathBuf::from(root))).unwrap()
+    }
+
+    fn as_refs<'a>(headers: &'a [(&'static str, String)]) -> Vec<(&'static str, &'a str)> {
+        headers.iter().map(|(n, v)| (*n, v.as_str())).collect()
+    }
+
+    #[test]
+    fn test_sign_verify_and_replay() {
+        let signer = HeaderSigner::from_key([7; KEY_LEN]);
+        let headers = signer.sign(&context());
+
+        assert_eq!(signer.verify(as_refs(&headers)).unwrap(), context());
+        let err = signer.verify(as_refs(&headers)).unwrap_err();
+        assert!(err.to_string().contains("Replayed"), "{}", err);
+    }
+
+    #[test]
+    fn test_forged_and_stale_headers_rejected() {
+        let signer = HeaderSigner::from_key([7; KEY_LEN]);
+
+        let mut forged = signer.sign(&context());
+        forged[1].1 = "/etc".to_string();
+        assert!(signer.verify(as_refs(&forged)).unwrap_err().to_string().contains("not signed"));
+
+        let other_key = HeaderSigner::from_key([8; KEY_LEN]).sign(&context());
+        assert!(signer.verify(as_refs(&other_key)).is_err());
+
+        let stale = signer.sign_with(&context(), unix_now() - 600, "abcd");
+        assert!(signer.verify(as_refs(&stale)).unwrap_err().to_string().contains("Expired"));
+
+        // A repeated header must not slip past the signature check
+        let mut duplicated = signer.sign(&context());
+        duplicated.push((X_KODEGEN_PWD, "/etc".to_string()));
+        let err = signer.verify(as_refs(&duplicated)).unwrap_err();
+        assert!(err.to_string().contains("Repeated x-kodegen-pwd"), "{}", err);
+        let mut duplicated = signer.sign(&context());
+        duplicated.insert(0, ("X-Kodegen-Pwd", "/etc".to_string()));
+        assert!(signer.verify(as_refs(&duplicated)).unwrap_err().to_string().contains("Repeated"));
+
+        let unsigned = context().to_headers();
+        assert!(signer.verify(as_refs(&unsigned)).unwrap_err().to_string().contains("Missing"));
+    }
+
+    #[test]
+    fn test_concurrent_key_creation_agrees() {
+        let temp = tempfile::tempdir().unwrap();
+        let signers: Vec<HeaderSigner> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8).map(|_| scope.spawn(|| HeaderSigner::open_in(temp.path()).unwrap())).collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let stored = std::fs::read(temp.path().join(INFRA_KEY_FILE)).unwrap();
+        assert!(signers.iter().all(|signer| signer.key[..] == stored[..]));
+    }
+
+    #[test]
+    fn test_key_file_created_private_and_reused() {
+        let temp = tempfile::tempdir().unwrap();
+        let signer = HeaderSigner::open_in(temp.path()).unwrap();
+        let headers = signer.sign(&context());
+
+        let reopened = HeaderSigner::open_in(temp.path()).unwrap();
+        assert!(reopened.verify(as_refs(&headers)).is_ok());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(temp.path().join(INFRA_KEY_FILE)).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
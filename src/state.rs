@@ -0,0 +1,493 @@
+//! Runtime state registry for daemons (PID and socket records)
+//!
+//! Each running service owns one record at `{state_dir}/{category}/{name}.json`:
+//!
+//! ```json
+//! {
+//!   "category": "browser",
+//!   "name": "default",
+//!   "pid": 4242,
+//!   "port": 30438,
+//!   "socket": null,
+//!   "started_at": "2025-01-01T12:00:00Z",
+//!   "version": "0.1.0"
+//! }
+//! ```
+//!
+//! A record whose PID is no longer alive is stale (typically left behind by a
+//! crash). Stale records are ignored by lookups and removed by
+//! [`StateRegistry::cleanup_stale()`]; registering over a stale record
+//! replaces it, while registering over a live one fails, so a record also
+//! acts as a lock on its `(category, name)`. Records are created without
+//! overwriting, so of several services registering at once exactly one wins.
+//!
+//! A PID reused by an unrelated process would keep a stale record alive. On
+//! Linux and Windows this is detected by comparing `started_at` with the
+//! process's start time; elsewhere a reused PID counts as alive.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tempfile::NamedTempFile;
+
+/// Allowed delay between a process starting and its record's `started_at`
+/// being earlier than the measured start time (clock tick and boot time rounding)
+const START_TIME_SLACK: chrono::TimeDelta = chrono::TimeDelta::seconds(2);
+
+/// A running service
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateRecord {
+    /// Service category (e.g. `browser`)
+    pub category: String,
+    /// Instance name within the category
+    pub name: String,
+    /// Process ID owning the record
+    pub pid: u32,
+    /// TCP port the service listens on
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Unix socket / named pipe the service listens on
+    #[serde(default)]
+    pub socket: Option<PathBuf>,
+    /// When the service started
+    pub started_at: DateTime<Utc>,
+    /// Service version
+    pub version: String,
+}
+
+impl StateRecord {
+    /// Record for the current process, started now
+    pub fn current(category: &str, name: &str, version: &str) -> Self {
+        Self {
+            category: category.to_string(),
+            name: name.to_string(),
+            pid: std::process::id(),
+            port: None,
+            socket: None,
+            started_at: Utc::now(),
+            version: version.to_string(),
+        }
+    }
+
+    /// Set the TCP port
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Set the socket path
+    pub fn with_socket(mut self, socket: PathBuf) -> Self {
+        self.socket = Some(socket);
+        self
+    }
+
+    /// Whether the owning process is still running
+    ///
+    /// A process that started after the record did has reused the PID, and
+    /// does not keep the record alive.
+    pub fn is_alive(&self) -> bool {
+        is_pid_alive(self.pid)
+            && process_started_at(self.pid).is_none_or(|started| started <= self.started_at + START_TIME_SLACK)
+    }
+}
+
+/// Registry of [`StateRecord`]s under `state_dir()`
+#[derive(Debug, Clone)]
+pub struct StateRegistry {
+    dir: PathBuf,
+}
+
+impl StateRegistry {
+    /// Open the registry in `state_dir()`
+    pub fn open() -> Result<Self> {
+        Ok(Self::at(crate::KodegenConfig::state_dir()?))
+    }
+
+    /// Open a registry rooted at an explicit directory
+    pub fn at(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Root directory of the registry
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path of the record for `(category, name)`
+    pub fn record_path(&self, category: &str, name: &str) -> Result<PathBuf> {
+        for part in [category, name] {
+            crate::validation::validate_name(part).map_err(|e| anyhow::anyhow!("Invalid state record name: {}", e))?;
+        }
+        Ok(self.dir.join(category).join(format!("{}.json", name)))
+    }
+
+    /// Write a record, taking ownership of `(category, name)`
+    ///
+    /// A stale record is replaced; a live record owned by another process is
+    /// an error.
+    ///
+    /// The record is created without overwriting: a stale record is first
+    /// removed (renamed aside and re-checked, as in [`cleanup_stale()`](Self::cleanup_stale)),
+    /// so of several processes registering at once only one succeeds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if another live process owns the record or the file
+    /// cannot be written.
+    pub fn register(&self, record: &StateRecord) -> Result<PathBuf> {
+        let path = self.record_path(&record.category, &record.name)?;
+        let dir = path.parent().unwrap_or(&self.dir);
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create state directory: {}", dir.display()))?;
+
+        let mut temp_file = NamedTempFile::new_in(dir)?;
+        temp_file.write_all(serde_json::to_string_pretty(record)?.as_bytes())?;
+
+        loop {
+            let error = match temp_file.persist_noclobber(&path) {
+                Ok(_) => return Ok(path),
+                Err(e) => e,
+            };
+            if error.error.kind() != std::io::ErrorKind::AlreadyExists {
+                return Err(error.error)
+                    .with_context(|| format!("Failed to write state record: {}", path.display()));
+            }
+            temp_file = error.file;
+
+            let existing = read_record(&path)?;
+            match &existing {
+                // Our own record is ours to overwrite
+                Some(existing) if existing.pid == record.pid => {
+                    temp_file.persist(&path)?;
+                    return Ok(path);
+                }
+                Some(existing) if existing.is_alive() => bail!(
+                    "{}/{} is already running (pid {}, started {})",
+                    existing.category,
+                    existing.name,
+                    existing.pid,
+                    existing.started_at
+                ),
+                // Stale or malformed: remove it unless it changed meanwhile, then retry
+                _ => {
+                    remove_if_unchanged(&path, existing.as_ref())?;
+                }
+            }
+        }
+    }
+
+    /// Remove a record owned by the current process
+    ///
+    /// Records owned by other processes are left alone. Returns whether a
+    /// record was removed.
+    pub fn unregister(&self, category: &str, name: &str) -> Result<bool> {
+        let path = self.record_path(category, name)?;
+        match read_record(&path)? {
+            Some(record) if record.pid == std::process::id() => {
+                fs::remove_file(&path)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Get the live record for `(category, name)`
+    pub fn get(&self, category: &str, name: &str) -> Result<Option<StateRecord>> {
+        Ok(read_record(&self.record_path(category, name)?)?.filter(StateRecord::is_alive))
+    }
+
+    /// List all live records, sorted by category and name
+    pub fn list(&self) -> Result<Vec<StateRecord>> {
+        Ok(self.all()?.into_iter().map(|(_, r)| r).filter(StateRecord::is_alive).collect())
+    }
+
+    /// Remove stale records (and their socket files), returning them
+    ///
+    /// Each record is first renamed aside, then re-checked, so a service that
+    /// re-registered in the meantime keeps its new record.
+    pub fn cleanup_stale(&self) -> Result<Vec<StateRecord>> {
+        let mut removed = Vec::new();
+        for (path, record) in self.all()? {
+            if record.is_alive() {
+                continue;
+            }
+
+            if !remove_if_unchanged(&path, Some(&record))? {
+                continue;
+            }
+            if let Some(socket) = &record.socket
+                && fs::symlink_metadata(socket).is_ok_and(|m| !m.is_dir())
+            {
+                let _ = fs::remove_file(socket);
+            }
+            log::info!("Removed stale state record {}/{} (pid {})", record.category, record.name, record.pid);
+            removed.push(record);
+        }
+        Ok(removed)
+    }
+
+    /// Read every record file, sorted by category and name
+    fn all(&self) -> Result<Vec<(PathBuf, StateRecord)>> {
+        let mut records = Vec::new();
+        let categories = match fs::read_dir(&self.dir) {
+            Ok(categories) => categories,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(records),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read state directory: {}", self.dir.display())),
+        };
+
+        for category in categories {
+            let category = category?;
+            if !category.file_type()?.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(category.path())? {
+                let path = entry?.path();
+                if path.extension().is_some_and(|ext| ext == "json")
+                    && let Some(record) = read_record(&path)?
+                {
+                    records.push((path, record));
+                }
+            }
+        }
+        records.sort_by(|(_, a), (_, b)| (&a.category, &a.name).cmp(&(&b.category, &b.name)));
+        Ok(records)
+    }
+}
+
+/// Remove a record file if it still holds `expected`, returning whether it was removed
+///
+/// The file is first renamed aside, then re-checked, so a service that
+/// re-registered in the meantime keeps its new record.
+fn remove_if_unchanged(path: &Path, expected: Option<&StateRecord>) -> Result<bool> {
+    static ASIDE_COUNTER: AtomicU64 = AtomicU64::new(0);
+    let aside = path.with_extension(format!(
+        "stale-{}-{}",
+        std::process::id(),
+        ASIDE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    if fs::rename(path, &aside).is_err() {
+        return Ok(false); // Already removed by someone else
+    }
+    if read_record(&aside)?.as_ref() == expected {
+        fs::remove_file(&aside)?;
+        return Ok(true);
+    }
+    // Replaced between reading and renaming: put it back unless re-registered again
+    if !path.exists() {
+        fs::rename(&aside, path)?;
+    } else {
+        fs::remove_file(&aside)?;
+    }
+    Ok(false)
+}
+
+/// Read a record, treating a missing or unparseable file as absent
+fn read_record(path: &Path) -> Result<Option<StateRecord>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read state record: {}", path.display())),
+    };
+    match serde_json::from_str(&content) {
+        Ok(record) => Ok(Some(record)),
+        Err(e) => {
+            log::warn!("Ignoring malformed state record {}: {}", path.display(), e);
+            Ok(None)
+        }
+    }
+}
+
+/// Check whether a process with this PID exists
+#[cfg(unix)]
+pub(crate) fn is_pid_alive(pid: u32) -> bool {
+    use nix::errno::Errno;
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+
+    let Ok(pid) = i32::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 performs error checking only; EPERM means it exists but belongs to another user
+    matches!(kill(Pid::from_raw(pid), None), Ok(()) | Err(Errno::EPERM))
+}
+
+/// Check whether a process with this PID exists
+#[cfg(windows)]
+pub(crate) fn is_pid_alive(pid: u32) -> bool {
+    use windows::Win32::Foundation::{CloseHandle, STILL_ACTIVE};
+    use windows::Win32::System::Threading::{GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+
+    // SAFETY: the handle is checked by OpenProcess and closed before returning
+    unsafe {
+        let Ok(handle) = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) else {
+            return false;
+        };
+        let mut code = 0u32;
+        let alive = GetExitCodeProcess(handle, &mut code).is_ok() && code == STILL_ACTIVE.0 as u32;
+        let _ = CloseHandle(handle);
+        alive
+    }
+}
+
+/// When the process with this PID started, where the platform reports it
+#[cfg(target_os = "linux")]
+fn process_started_at(pid: u32) -> Option<DateTime<Utc>> {
+    use nix::unistd::{sysconf, SysconfVar};
+
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // Fields follow the parenthesized command name (which may contain spaces);
+    // `starttime`, in clock ticks since boot, is field 22 of the whole line
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let ticks: i64 = fields.get(19)?.parse().ok()?;
+    let boot: i64 = fs::read_to_string("/proc/stat")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()?;
+    let hz = (sysconf(SysconfVar::CLK_TCK).ok()?? as i64).max(1);
+    DateTime::from_timestamp_millis(boot * 1000 + ticks * 1000 / hz)
+}
+
+/// When the process with this PID started, where the platform reports it
+#[cfg(windows)]
+fn process_started_at(pid: u32) -> Option<DateTime<Utc>> {
+    use windows::Win32::Foundation::{CloseHandle, FILETIME};
+    use windows::Win32::System::Threading::{GetProcessTimes, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+
+    /// Seconds between 1601-01-01 (FILETIME epoch) and 1970-01-01
+    const EPOCH_OFFSET_SECS: i64 = 11_644_473_600;
+
+    // SAFETY: the handle is checked by OpenProcess and closed before returning
+    let created = unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let (mut created, mut exited, mut kernel, mut user) =
+            (FILETIME::default(), FILETIME::default(), FILETIME::default(), FILETIME::default());
+        let result = GetProcessTimes(handle, &mut created, &mut exited, &mut kernel, &mut user);
+        let _ = CloseHandle(handle);
+        result.ok()?;
+        created
+    };
+    // 100ns intervals since 1601
+    let intervals = (u64::from(created.dwHighDateTime) << 32) | u64::from(created.dwLowDateTime);
+    let millis = i64::try_from(intervals / 10_000).ok()? - EPOCH_OFFSET_SECS * 1000;
+    DateTime::from_timestamp_millis(millis)
+}
+
+/// When the process with this PID started, where the platform reports it
+#[cfg(not(any(target_os = "linux", windows)))]
+fn process_started_at(_pid: u32) -> Option<DateTime<Utc>> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dead_pid() -> u32 {
+        let mut child = std::process::Command::new(if cfg!(windows) { "cmd" } else { "true" })
+            .args(if cfg!(windows) { &["/C", "exit"][..] } else { &[][..] })
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        pid
+    }
+
+    #[test]
+    fn test_register_lock_and_stale_cleanup() {
+        let temp = tempfile::tempdir().unwrap();
+        let registry = StateRegistry::at(temp.path().to_path_buf());
+
+        let live = StateRecord::current("browser", "default", "1.0.0").with_port(30438);
+        registry.register(&live).unwrap();
+
+        let mut stale = StateRecord { pid: dead_pid(), ..StateRecord::current("git", "default", "1.0.0") };
+        let socket = temp.path().join("git.sock");
+        fs::write(&socket, "").unwrap();
+        stale.socket = Some(socket.clone());
+        registry.register(&stale).unwrap();
+
+        assert_eq!(registry.get("browser", "default").unwrap(), Some(live.clone()));
+        assert_eq!(registry.get("git", "default").unwrap(), None);
+        assert_eq!(registry.list().unwrap(), vec![live.clone()]);
+
+        assert_eq!(registry.cleanup_stale().unwrap(), vec![stale.clone()]);
+        assert!(!registry.record_path("git", "default").unwrap().exists());
+        assert!(!socket.exists());
+
+        // Stale records are replaced on register
+        registry.register(&stale).unwrap();
+        let fresh = StateRecord::current("git", "default", "1.1.0");
+        registry.register(&fresh).unwrap();
+        assert_eq!(registry.get("git", "default").unwrap(), Some(fresh));
+
+        assert!(registry.unregister("browser", "default").unwrap());
+        assert!(registry.list().unwrap().iter().all(|r| r.category != "browser"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_live_record_is_not_taken_over() {
+        let temp = tempfile::tempdir().unwrap();
+        let registry = StateRegistry::at(temp.path().to_path_buf());
+
+        // Our parent is alive and a different pid
+        let parent = StateRecord { pid: std::os::unix::process::parent_id(), ..StateRecord::current("db", "main", "1") };
+        registry.register(&parent).unwrap();
+        let err = registry.register(&StateRecord::current("db", "main", "1")).unwrap_err();
+        assert!(err.to_string().contains("already running"), "{}", err);
+        assert!(!registry.unregister("db", "main").unwrap(), "not ours to remove");
+    }
+
+    #[test]
+    fn test_concurrent_registration_has_one_winner() {
+        let temp = tempfile::tempdir().unwrap();
+        let registry = StateRegistry::at(temp.path().to_path_buf());
+        // A stale record to race over
+        let stale = StateRecord { pid: dead_pid(), ..StateRecord::current("db", "main", "1") };
+        registry.register(&stale).unwrap();
+
+        // Distinct live pids, each registering from its own thread
+        let mut children: Vec<_> = (0..4)
+            .map(|_| {
+                std::process::Command::new(if cfg!(windows) { "cmd" } else { "sleep" })
+                    .args(if cfg!(windows) { &["/C", "ping", "-n", "6", "127.0.0.1"][..] } else { &["5"][..] })
+                    .spawn()
+                    .unwrap()
+            })
+            .collect();
+        let started_at = Utc::now();
+        let results: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = children
+                .iter()
+                .map(|child| {
+                    let record = StateRecord { pid: child.id(), started_at, ..StateRecord::current("db", "main", "1") };
+                    let registry = &registry;
+                    scope.spawn(move || registry.register(&record).is_ok())
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        for child in &mut children {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+
+        assert_eq!(results.iter().filter(|won| **won).count(), 1, "{:?}", results);
+    }
+
+    #[cfg(any(target_os = "linux", windows))]
+    #[test]
+    fn test_reused_pid_is_not_alive() {
+        let mut record = StateRecord::current("db", "main", "1");
+        assert!(record.is_alive());
+        // Our pid, but recorded long before this process started
+        record.started_at = DateTime::from_timestamp(1_000_000_000, 0).unwrap();
+        assert!(!record.is_alive());
+    }
+}
@@ -0,0 +1,122 @@
+//! Teardown: reversing `init_structure()`
+//!
+//! Teardown reuses the [`InitPlan`] machinery, so the same report shows what
+//! would be removed before anything is. [`TeardownOptions`] defaults to a
+//! dry-run; callers show the report, ask for confirmation, and run again with
+//! `dry_run: false`.
+
+use anyhow::{bail, Result};
+use std::path::Path;
+
+use crate::init::{InitAction, InitPlan, InitReport};
+
+/// How much of the user tree to remove
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TeardownScope {
+    /// Only `cache/`
+    #[default]
+    Cache,
+    /// `cache/` and `state/`
+    CacheAndState,
+    /// The whole user config root (config, data, logs, binaries, ...)
+    Everything,
+}
+
+/// Options for [`teardown()`](crate::KodegenConfig::teardown)
+#[derive(Debug, Clone)]
+pub struct TeardownOptions {
+    /// Only report what would be removed (default: `true`)
+    pub dry_run: bool,
+    /// Which parts of the user tree to remove
+    pub scope: TeardownScope,
+    /// Remove the `.kodegen/` ignore rules init added to the current repository
+    /// (default: `false`)
+    ///
+    /// Only lines init recorded adding are removed. Un-ignoring `.kodegen/`
+    /// can put private config into the next commit, so this is opt-in and
+    /// independent of [`scope`](Self::scope).
+    pub ignore_rules: bool,
+}
+
+impl Default for TeardownOptions {
+    fn default() -> Self {
+        Self { dry_run: true, scope: TeardownScope::default(), ignore_rules: false }
+    }
+}
+
+/// Build the teardown plan
+///
+/// Ignore rules (if requested) are removed first, then directories. The local `.kodegen/`
+/// directory is never removed since it may hold committed project config.
+pub(crate) fn plan(options: &TeardownOptions) -> Result<InitPlan> {
+    let mut plan = InitPlan::default();
+
+    if options.ignore_rules
+        && let Ok(git_root) = crate::git::find_git_root()
+    {
+        plan.actions.extend(crate::init::plan_ignore_rule_removal(&git_root)?);
+    }
+
+    let paths = match options.scope {
+        TeardownScope::Cache => vec![crate::KodegenConfig::cache_dir()?],
+        TeardownScope::CacheAndState => {
+            vec![crate::KodegenConfig::cache_dir()?, crate::KodegenConfig::state_dir()?]
+        }
+        TeardownScope::Everything => vec![crate::KodegenConfig::user_config_dir()?],
+    };
+    for path in paths {
+        check_removable(&path)?;
+        plan.actions.push(InitAction::RemoveDir { path });
+    }
+
+    Ok(plan)
+}
+
+/// Build and execute the teardown plan
+pub(crate) fn teardown(options: &TeardownOptions) -> Result<InitReport> {
+    Ok(plan(options)?.execute(options.dry_run))
+}
+
+/// Refuse to delete paths that cannot be a kodegen directory
+///
+/// Guards against a misconfigured `XDG_CONFIG_HOME` or similar resolving the
+/// user root to `/` or the home directory.
+fn check_removable(path: &Path) -> Result<()> {
+    let is_home = dirs::home_dir().is_some_and(|home| home == path);
+    if path.parent().is_none() || is_home || path.components().count() < 3 {
+        bail!("Refusing to remove {}: not a kodegen directory", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_check_removable_rejects_roots() {
+        assert!(check_removable(Path::new("/")).is_err());
+        if let Some(home) = dirs::home_dir() {
+            assert!(check_removable(&home).is_err());
+        }
+        assert!(check_removable(Path::new("/home/user/.config/kodegen/cache")).is_ok());
+    }
+
+    #[test]
+    fn test_remove_dir_dry_run_then_apply() {
+        let temp = tempfile::tempdir().unwrap();
+        let cache = temp.path().join("cache");
+        fs::create_dir_all(cache.join("downloads")).unwrap();
+        fs::write(cache.join("downloads/file"), "x").unwrap();
+
+        let plan = InitPlan { actions: vec![InitAction::RemoveDir { path: cache.clone() }], ..Default::default() };
+        let report = plan.execute(true);
+        assert_eq!(report.applied().count(), 1);
+        assert!(cache.exists());
+
+        assert!(plan.execute(false).is_success());
+        assert!(!cache.exists());
+        assert_eq!(plan.execute(false).applied().count(), 0, "second run is a no-op");
+    }
+}
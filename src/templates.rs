@@ -0,0 +1,241 @@
+//! Project scaffolding templates for `.kodegen/`
+//!
+//! A template is a set of files written into the git-local `.kodegen/`
+//! directory by [`init_structure_with()`](crate::KodegenConfig::init_structure_with)
+//! when [`InitOptions::template`](crate::InitOptions::template) is set.
+//!
+//! Templates come from two places, user templates taking precedence:
+//! - `user_config_dir()/templates/{name}/` - every file below it, keeping the
+//!   relative layout
+//! - Built-in templates: [`BUILTIN_TEMPLATES`]
+//!
+//! File contents may use `{{repo_name}}` (the git root's directory name) and
+//! `{{git_root}}` (its absolute path). Other `{{...}}` sequences are left as is.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Subdirectory of `user_config_dir()` holding user templates
+pub const TEMPLATES_DIR: &str = "templates";
+
+/// Names of the built-in templates
+pub const BUILTIN_TEMPLATES: &[&str] = &["minimal", "node", "rust"];
+
+const README: &str = "# kodegen configuration for {{repo_name}}
+
+This directory holds project-local kodegen configuration. Files here take
+precedence over the user-global configuration.
+
+- `toolset/` - toolsets available in this repository
+- `claude/` - Claude agent configuration
+- `local/` - personal overrides, never committed
+";
+
+const MINIMAL_TOOLSET: &str = r#"{
+  "$schema": "../schemas/toolset.schema.json",
+  "description": "Default tools for {{repo_name}}",
+  "tools": [],
+  "categories": ["filesystem", "git"]
+}
+"#;
+
+const RUST_TOOLSET: &str = r#"{
+  "$schema": "../schemas/toolset.schema.json",
+  "description": "Rust development in {{repo_name}}",
+  "tools": ["terminal", "process_list", "process_kill"],
+  "categories": ["filesystem", "git", "reasoner"]
+}
+"#;
+
+const NODE_TOOLSET: &str = r#"{
+  "$schema": "../schemas/toolset.schema.json",
+  "description": "Node.js development in {{repo_name}}",
+  "tools": ["terminal", "process_list", "process_kill"],
+  "categories": ["filesystem", "git", "browser"]
+}
+"#;
+
+/// Files of a built-in template, as (relative path, contents)
+fn builtin_files(name: &str) -> Option<Vec<(&'static str, &'static str)>> {
+    let (toolset_file, toolset) = match name {
+        "minimal" => ("toolset/default.json", MINIMAL_TOOLSET),
+        "rust" => ("toolset/rust.json", RUST_TOOLSET),
+        "node" => ("toolset/node.json", NODE_TOOLSET),
+        _ => return None,
+    };
+    Some(vec![("README.md", README), (toolset_file, toolset)])
+}
+
+/// Values substituted into template files
+#[derive(Debug, Clone)]
+pub(crate) struct TemplateVars {
+    pub repo_name: String,
+    pub git_root: PathBuf,
+}
+
+impl TemplateVars {
+    /// Variables for the repository rooted at `git_root`
+    pub(crate) fn for_git_root(git_root: &Path) -> Self {
+        let repo_name = git_root
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "project".to_string());
+        Self { repo_name, git_root: git_root.to_path_buf() }
+    }
+
+    /// Substitute variables into a template file
+    ///
+    /// In `.json` files the values are JSON-escaped, since variables appear
+    /// inside string literals there (e.g. a Windows root `C:\work\repo`).
+    fn render(&self, path: &Path, contents: &str) -> String {
+        let git_root = self.git_root.to_string_lossy();
+        let (repo_name, git_root) = if path.extension().is_some_and(|ext| ext == "json") {
+            (json_escape(&self.repo_name), json_escape(&git_root))
+        } else {
+            (self.repo_name.clone(), git_root.into_owned())
+        };
+        contents.replace("{{repo_name}}", &repo_name).replace("{{git_root}}", &git_root)
+    }
+}
+
+/// Escape a value for use inside a JSON string literal
+fn json_escape(value: &str) -> String {
+    let quoted = serde_json::Value::from(value).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+/// List available template names (built-in and user), sorted and deduplicated
+pub fn template_names() -> Result<Vec<String>> {
+    let mut names: Vec<String> = BUILTIN_TEMPLATES.iter().map(|n| n.to_string()).collect();
+
+    let user_dir = crate::KodegenConfig::user_config_dir()?.join(TEMPLATES_DIR);
+    if let Ok(entries) = fs::read_dir(&user_dir) {
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|t| t.is_dir())
+                && let Some(name) = entry.file_name().to_str()
+                && crate::validation::validate_name(name).is_ok()
+            {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// Render a template into `(path under local_dir, contents)` pairs
+///
+/// # Errors
+///
+/// Returns an error if the name is invalid, no such template exists, or a
+/// user template file cannot be read.
+pub(crate) fn render(name: &str, local_dir: &Path, vars: &TemplateVars) -> Result<Vec<(PathBuf, String)>> {
+    crate::validation::validate_name(name).map_err(|e| anyhow!("Invalid template name: {}", e))?;
+
+    let user_dir = crate::KodegenConfig::user_config_dir()?.join(TEMPLATES_DIR).join(name);
+    let files: BTreeMap<PathBuf, String> = if user_dir.is_dir() {
+        read_user_template(&user_dir)?
+    } else if let Some(files) = builtin_files(name) {
+        files.into_iter().map(|(path, contents)| (PathBuf::from(path), contents.to_string())).collect()
+    } else {
+        bail!(
+            "Unknown template '{}'. Available templates: {}",
+            name,
+            template_names()?.join(", ")
+        );
+    };
+
+    Ok(files
+        .into_iter()
+        .map(|(relative, contents)| {
+            let contents = vars.render(&relative, &contents);
+            (local_dir.join(relative), contents)
+        })
+        .collect())
+}
+
+/// Read every regular file below a user template directory
+///
+/// Symlinks are skipped so a template cannot pull in files from elsewhere.
+fn read_user_template(dir: &Path) -> Result<BTreeMap<PathBuf, String>> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(current) = pending.pop() {
+        let entries = fs::read_dir(&current)
+            .with_context(|| format!("Failed to read template directory: {}", current.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_file() {
+                let contents = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read template file: {}", path.display()))?;
+                let relative = path.strip_prefix(dir).unwrap_or(&path).to_path_buf();
+                files.insert(relative, contents);
+            } else {
+                log::warn!("Skipping non-regular file in template: {}", path.display());
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_templates_render_valid_files() {
+        let vars = TemplateVars::for_git_root(Path::new("/work/my-repo"));
+        for name in BUILTIN_TEMPLATES {
+            let files = builtin_files(name).unwrap();
+            for (path, contents) in files {
+                let rendered = vars.render(Path::new(path), contents);
+                assert!(!rendered.contains("{{"), "{}/{} has unrendered variables", name, path);
+                if path.ends_with(".json") {
+                    serde_json::from_str::<serde_json::Value>(&rendered).unwrap();
+                }
+                if path.starts_with("toolset/") {
+                    let errors =
+                        crate::schema::validate_str(&rendered, &crate::schema::toolset_schema(), Path::new(path));
+                    assert!(errors.is_empty(), "{}/{}: {:?}", name, path, errors);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_user_template_layout_and_substitution() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("toolset")).unwrap();
+        fs::write(temp.path().join("toolset/ci.json"), "{\"description\": \"{{repo_name}} CI\"}").unwrap();
+        fs::write(temp.path().join("NOTES.md"), "root: {{git_root}} {{unknown}}").unwrap();
+
+        let vars = TemplateVars::for_git_root(Path::new("/src/app"));
+        let files = read_user_template(temp.path()).unwrap();
+        let ci = Path::new("toolset/ci.json");
+        assert_eq!(vars.render(ci, &files[ci]), "{\"description\": \"app CI\"}");
+        let notes = Path::new("NOTES.md");
+        assert_eq!(vars.render(notes, &files[notes]), "root: /src/app {{unknown}}");
+    }
+
+    #[test]
+    fn test_json_values_are_escaped() {
+        let vars = TemplateVars { repo_name: "my \"repo\"".to_string(), git_root: PathBuf::from(r"C:\work\repo") };
+        let template = r#"{ "name": "{{repo_name}}", "root": "{{git_root}}" }"#;
+
+        let rendered: serde_json::Value = serde_json::from_str(&vars.render(Path::new("config.json"), template)).unwrap();
+        assert_eq!(rendered["name"], "my \"repo\"");
+        assert_eq!(rendered["root"], r"C:\work\repo");
+
+        assert_eq!(vars.render(Path::new("README.md"), "{{git_root}}"), r"C:\work\repo");
+    }
+}
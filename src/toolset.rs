@@ -1,11 +1,127 @@
-use anyhow::{anyhow, Result};
-use std::path::PathBuf;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::{Category, Endpoint, PortMap};
+
+/// Toolset file contents (`toolset/{name}.json`)
+///
+/// ```json
+/// {
+///   "$schema": "../schemas/toolset.schema.json",
+///   "description": "Everyday coding tools",
+///   "tools": ["fs_read_file", "fs_edit_block", "terminal"],
+///   "categories": ["git"]
+/// }
+/// ```
+///
+/// Unknown fields are rejected so typos like `toolz` fail loudly instead of
+/// silently enabling nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Toolset {
+    /// JSON Schema reference for editor support (ignored at runtime)
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+
+    /// Human-readable description of the toolset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Individual tool names to enable (deprecated aliases are rewritten on load)
+    #[serde(default)]
+    pub tools: Vec<String>,
+
+    /// Categories whose tools are all enabled (unknown names fail to parse)
+    #[serde(default)]
+    pub categories: Vec<Category>,
+}
+
+/// Servers a toolset needs, from [`Toolset::routes()`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolsetRoutes {
+    /// Tool categories the toolset uses, sorted by name
+    pub categories: Vec<&'static Category>,
+    /// Server categories to start with their endpoints, sorted by name
+    ///
+    /// Memory tools are served by candle-agent, so `memory` appears in
+    /// `categories` but its server is `candle_agent`.
+    pub servers: Vec<(&'static Category, Endpoint)>,
+    /// Tool names not in the [`TOOLS`](crate::TOOLS) registry
+    pub unknown_tools: Vec<String>,
+}
+
+impl Toolset {
+    /// Categories and servers needed to serve this toolset, with configured endpoints
+    ///
+    /// Uses the [`port_map()`](crate::KodegenConfig::port_map) selection: a
+    /// server whose transport is `unix` gets its
+    /// [`service_socket_path()`](crate::KodegenConfig::service_socket_path),
+    /// every other server its port. See [`routes_with()`](Self::routes_with).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the port overrides are invalid or a socket path
+    /// cannot be derived.
+    pub fn routes(&self) -> Result<ToolsetRoutes> {
+        self.routes_with(&crate::KodegenConfig::port_map()?, &crate::KodegenConfig::socket_dir()?)
+    }
+
+    /// Categories and servers needed to serve this toolset, with endpoints
+    /// selected by `ports` (sockets in `socket_dir`)
+    ///
+    /// Tools resolve to categories via the [`TOOLS`](crate::TOOLS) registry;
+    /// unknown tools are reported in [`ToolsetRoutes::unknown_tools`] and
+    /// start nothing. Servers that a running instance published elsewhere are
+    /// found with [`resolve_service_endpoint()`](crate::KodegenConfig::resolve_service_endpoint).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a socket path does not fit `sun_path` even in the temp dir.
+    pub fn routes_with(&self, ports: &PortMap, socket_dir: &Path) -> Result<ToolsetRoutes> {
+        let mut categories: BTreeMap<&'static str, &'static Category> = BTreeMap::new();
+        let mut unknown_tools = Vec::new();
+
+        for tool in &self.tools {
+            match crate::constants::category_of(tool) {
+                Some(category) => {
+                    categories.insert(category.name, category);
+                }
+                None if !unknown_tools.contains(tool) => unknown_tools.push(tool.clone()),
+                None => {}
+            }
+        }
+        for category in &self.categories {
+            if let Some(category) = Category::from_name(category.name) {
+                categories.insert(category.name, category);
+            }
+        }
+
+        let mut servers: BTreeMap<&'static str, (&'static Category, Endpoint)> = BTreeMap::new();
+        for category in categories.values() {
+            if let Some(server) = category.backend()
+                && !servers.contains_key(server.name)
+            {
+                let endpoint = crate::services::configured(ports, socket_dir, server)?;
+                servers.insert(server.name, (server, endpoint));
+            }
+        }
+
+        Ok(ToolsetRoutes {
+            categories: categories.into_values().collect(),
+            servers: servers.into_values().collect(),
+            unknown_tools,
+        })
+    }
+}
 
 /// Resolve toolset file path with local > user precedence
 ///
 /// **Search order**:
-/// 1. `${git_root}/.kodegen/toolset/{name}.json`
-/// 2. `$XDG_CONFIG_HOME/kodegen/toolset/{name}.json`
+/// 1. `${git_root}/.kodegen/local/toolset/{name}.json` (private)
+/// 2. `${git_root}/.kodegen/toolset/{name}.json`
+/// 3. `$XDG_CONFIG_HOME/kodegen/toolset/{name}.json`
 ///
 /// # Errors
 ///
@@ -15,8 +131,8 @@ pub fn resolve(name: &str) -> Result<PathBuf> {
     let filename = format!("{}.json", name);
     let mut searched_paths = Vec::new();
 
-    // Check local .kodegen/toolset/ first
-    if let Ok(local_dir) = crate::KodegenConfig::local_config_dir() {
+    // Check local .kodegen/local/toolset/ and .kodegen/toolset/ first
+    for local_dir in crate::local_layers() {
         let local_path = local_dir.join("toolset").join(&filename);
         searched_paths.push(local_path.display().to_string());
         if let Some(path) = crate::try_resolve_in_dir(&local_dir, "toolset", &filename) {
@@ -25,7 +141,7 @@ pub fn resolve(name: &str) -> Result<PathBuf> {
     }
 
     // Check user global config/toolset/
-    let user_dir = crate::KodegenConfig::user_config_dir()?;
+    let user_dir = crate::KodegenConfig::user_layer_dir()?;
     let user_path = user_dir.join("toolset").join(&filename);
     searched_paths.push(user_path.display().to_string());
     if let Some(path) = crate::try_resolve_in_dir(&user_dir, "toolset", &filename) {
@@ -39,3 +155,93 @@ pub fn resolve(name: &str) -> Result<PathBuf> {
         searched_paths.join("\n  ")
     ))
 }
+
+/// Resolve and parse a toolset file by name
+pub fn load(name: &str) -> Result<Toolset> {
+    load_from_path(&resolve(name)?)
+}
+
+/// Parse a toolset file at an explicit path
+pub fn load_from_path(path: &Path) -> Result<Toolset> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read toolset file: {}", path.display()))?;
+    let mut toolset: Toolset = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid toolset file: {}", path.display()))?;
+    toolset.tools = canonicalize_tools(toolset.tools, path);
+    Ok(toolset)
+}
+
+/// Rewrite deprecated tool aliases to canonical names, dropping duplicates
+fn canonicalize_tools(tools: Vec<String>, path: &Path) -> Vec<String> {
+    let mut canonical: Vec<String> = Vec::with_capacity(tools.len());
+    for name in tools {
+        let resolved = crate::canonical_tool_name(&name);
+        if resolved != name {
+            log::warn!(
+                "Toolset {} uses deprecated tool name '{}'; use '{}' instead",
+                path.display(),
+                name,
+                resolved
+            );
+        }
+        if !canonical.iter().any(|t| t == resolved) {
+            canonical.push(resolved.to_string());
+        }
+    }
+    canonical
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::*;
+
+    #[test]
+    fn test_routes() {
+        let toolset = Toolset {
+            tools: vec![
+                GIT_COMMIT.to_string(),
+                GIT_STATUS.to_string(),
+                MEMORY_RECALL.to_string(),
+                "fs_raed_file".to_string(),
+                "fs_raed_file".to_string(),
+            ],
+            categories: vec![*CATEGORY_CANDLE_AGENT, *CATEGORY_FILESYSTEM],
+            ..Default::default()
+        };
+
+        let socket_dir = Path::new("/run/kodegen");
+        let routes = toolset.routes_with(&PortMap::defaults(), socket_dir).unwrap();
+        assert_eq!(routes.categories, [CATEGORY_CANDLE_AGENT, CATEGORY_FILESYSTEM, CATEGORY_GIT, CATEGORY_MEMORY]);
+        assert_eq!(
+            routes.servers,
+            [
+                (CATEGORY_CANDLE_AGENT, Endpoint::Tcp(PORT_CANDLE_AGENT)),
+                (CATEGORY_FILESYSTEM, Endpoint::Tcp(PORT_FILESYSTEM)),
+                (CATEGORY_GIT, Endpoint::Tcp(PORT_GIT))
+            ]
+        );
+        assert_eq!(routes.unknown_tools, ["fs_raed_file"]);
+
+        // A category configured for a Unix socket routes to its socket path
+        let mut ports = PortMap::defaults();
+        ports.set_transport(CATEGORY_GIT, crate::Transport::Unix).unwrap();
+        let routes = toolset.routes_with(&ports, socket_dir).unwrap();
+        assert_eq!(routes.servers[2], (CATEGORY_GIT, Endpoint::Unix(socket_dir.join("git.sock"))));
+        assert_eq!(routes.servers[1], (CATEGORY_FILESYSTEM, Endpoint::Tcp(PORT_FILESYSTEM)));
+
+        assert_eq!(Toolset::default().routes_with(&ports, socket_dir).unwrap(), ToolsetRoutes::default());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_load_normalizes_aliases() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("legacy.json");
+        let tools = [GIT_TAG, GIT_STATUS, GIT_TAG_CREATE, INSPECT_TOOL_CALLS];
+        std::fs::write(&path, serde_json::json!({ "tools": tools }).to_string()).unwrap();
+
+        let toolset = load_from_path(&path).unwrap();
+        assert_eq!(toolset.tools, [GIT_TAG_CREATE, GIT_STATUS, INTROSPECTION_INSPECT_TOOL_CALLS]);
+    }
+}
@@ -34,6 +34,8 @@ const WINDOWS_RESERVED_NAMES: &[&str] = &[
 ///
 /// # Examples
 /// ```
+/// use kodegen_config::validate_name;
+///
 /// assert!(validate_name("core").is_ok());
 /// assert!(validate_name("my-toolset").is_ok());
 /// assert!(validate_name("toolset_v2").is_ok());
@@ -43,7 +45,6 @@ const WINDOWS_RESERVED_NAMES: &[&str] = &[
 /// assert!(validate_name("foo/bar").is_err());
 /// assert!(validate_name(".hidden").is_err());
 /// ```
-#[allow(dead_code)]
 pub fn validate_name(name: &str) -> Result<(), String> {
     // Rule 1: Reject empty or whitespace-only names
     if name.trim().is_empty() {
@@ -126,14 +127,16 @@ pub fn validate_name(name: &str) -> Result<(), String> {
 ///
 /// # Examples
 /// ```
-/// let base = Path::new("/home/user/.kodegen/toolset");
-/// let safe = Path::new("/home/user/.kodegen/toolset/core.json");
-/// let unsafe_path = Path::new("/etc/passwd");
-/// 
-/// assert!(verify_within_directory(safe, base));
-/// assert!(!verify_within_directory(unsafe_path, base));
+/// use kodegen_config::verify_within_directory;
+///
+/// let base = tempfile::tempdir().unwrap();
+/// let safe = base.path().join("core.json");
+/// std::fs::write(&safe, "{}").unwrap();
+/// let unsafe_path = base.path().join("..");
+///
+/// assert!(verify_within_directory(&safe, base.path()));
+/// assert!(!verify_within_directory(&unsafe_path, base.path()));
 /// ```
-#[allow(dead_code)]
 pub fn verify_within_directory(resolved_path: &Path, base_dir: &Path) -> bool {
     // Attempt to canonicalize both paths
     let canonical_resolved = match resolved_path.canonicalize() {
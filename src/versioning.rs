@@ -0,0 +1,340 @@
+//! Config schema versioning and automatic migrations
+//!
+//! Components register one migration function per version step for the
+//! config files they own. Those files carry a top-level `"version"` field
+//! (missing means version 1), which is managed by this module: it is removed
+//! before the file is deserialized and allowed by the file's schema. Files
+//! without registered migrations are not versioned, and a `version` field in
+//! them is ordinary configuration.
+//!
+//! ```rust
+//! use kodegen_config::register_migration;
+//!
+//! // daemon.json v1 -> v2: rename "listen" to "bind"
+//! register_migration("daemon.json", 1, |mut value| {
+//!     if let Some(map) = value.as_object_mut()
+//!         && let Some(listen) = map.remove("listen")
+//!     {
+//!         map.insert("bind".to_string(), listen);
+//!     }
+//!     Ok(value)
+//! });
+//! ```
+//!
+//! Migrations run on every [`load_config()`](crate::KodegenConfig::load_config).
+//! With [`LoadOptions::write_back`](crate::LoadOptions::write_back) the migrated
+//! file is written back after a timestamped backup is stored in
+//! `data_dir()/backups/`. The rewrite keeps key order but re-indents the file
+//! as pretty-printed JSON. [`migrate_config()`](crate::KodegenConfig::migrate_config)
+//! offers a dry-run that shows the diff a migration would make.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Name of the version field at the root of versioned config files
+pub const VERSION_FIELD: &str = "version";
+
+/// A single migration step, transforming version `N` into version `N + 1`
+///
+/// The `version` field is updated automatically after the step returns.
+pub type MigrationFn = fn(Value) -> Result<Value>;
+
+/// Type alias for the migration registry
+/// Keyed by config filename, then by source version
+type MigrationRegistry = parking_lot::RwLock<HashMap<String, BTreeMap<u64, MigrationFn>>>;
+
+/// Global registry of config migrations
+static MIGRATIONS: std::sync::LazyLock<MigrationRegistry> =
+    std::sync::LazyLock::new(|| parking_lot::RwLock::new(HashMap::new()));
+
+/// Register a migration from `from_version` to `from_version + 1` for a config file
+///
+/// Registering the same step twice replaces the previous function.
+pub fn register_migration(filename: &str, from_version: u64, migration: MigrationFn) {
+    MIGRATIONS
+        .write()
+        .entry(filename.to_string())
+        .or_default()
+        .insert(from_version, migration);
+}
+
+/// Whether a config file has registered migrations (and so a managed `version` field)
+pub(crate) fn is_versioned(filename: &str) -> bool {
+    MIGRATIONS.read().contains_key(filename)
+}
+
+/// Current (latest) version of a config file format
+///
+/// One past the highest registered migration step, or 1 if none are registered.
+pub fn current_version(filename: &str) -> u64 {
+    MIGRATIONS
+        .read()
+        .get(filename)
+        .and_then(|steps| steps.keys().next_back())
+        .map_or(1, |last| last + 1)
+}
+
+/// Options for [`migrate_config()`](crate::KodegenConfig::migrate_config)
+#[derive(Debug, Clone, Default)]
+pub struct MigrateOptions {
+    /// Compute the migration and diff without writing anything
+    pub dry_run: bool,
+}
+
+/// Result of migrating a single config file
+#[derive(Debug, Clone)]
+pub struct MigrationReport {
+    /// Config file that was examined
+    pub path: PathBuf,
+    /// Version found in the file
+    pub from_version: u64,
+    /// Version after migration
+    pub to_version: u64,
+    /// Unified diff between the original and migrated file (empty if unchanged)
+    pub diff: String,
+    /// Backup of the original file, if it was rewritten
+    pub backup: Option<PathBuf>,
+    /// Whether the migrated file was written back
+    pub written: bool,
+}
+
+impl MigrationReport {
+    /// Whether the file needed (or needs, for a dry-run) migrating
+    pub fn is_migrated(&self) -> bool {
+        self.from_version != self.to_version
+    }
+}
+
+/// Read the `version` field of a config document (missing means 1)
+pub(crate) fn version_of(value: &Value) -> Result<u64> {
+    match value.get(VERSION_FIELD) {
+        None => Ok(1),
+        Some(v) => v
+            .as_u64()
+            .filter(|v| *v >= 1)
+            .ok_or_else(|| anyhow!("Invalid \"{}\" field: expected a positive integer, found {}", VERSION_FIELD, v)),
+    }
+}
+
+/// Migrate a config document to the current version
+///
+/// Returns the migrated document, the version it started at and the version
+/// it ends at. Documents of files without registered migrations are returned
+/// unchanged as version 1, whatever their `version` field holds.
+///
+/// # Errors
+///
+/// Returns an error if the document is newer than this build supports, a
+/// migration step is missing, or a migration function fails.
+pub(crate) fn migrate_value(filename: &str, mut value: Value) -> Result<(Value, u64, u64)> {
+    if !is_versioned(filename) {
+        return Ok((value, 1, 1));
+    }
+    let from_version = version_of(&value)?;
+    let target = current_version(filename);
+
+    if from_version > target {
+        bail!(
+            "Config file '{}' has version {}, but this build only supports up to version {}. \
+             It was probably written by a newer kodegen",
+            filename,
+            from_version,
+            target
+        );
+    }
+
+    let steps = MIGRATIONS.read().get(filename).cloned().unwrap_or_default();
+    for version in from_version..target {
+        let step = steps.get(&version).ok_or_else(|| {
+            anyhow!("No migration registered for '{}' from version {} to {}", filename, version, version + 1)
+        })?;
+        value = step(value)
+            .with_context(|| format!("Migration of '{}' from version {} to {} failed", filename, version, version + 1))?;
+        if let Some(map) = value.as_object_mut() {
+            map.insert(VERSION_FIELD.to_string(), Value::from(version + 1));
+        }
+    }
+
+    Ok((value, from_version, target))
+}
+
+/// Migrate a config file on disk
+///
+/// `original` is the file's JSON content. Unless `dry_run` is set and the
+/// file needs migrating, the original is backed up to `backup_dir` and the
+/// migrated document is written back atomically.
+pub(crate) fn migrate_file(path: &Path, original: &Value, backup_dir: &Path, dry_run: bool) -> Result<MigrationReport> {
+    let filename = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .with_context(|| format!("Invalid config file path: {}", path.display()))?;
+
+    let (migrated, from_version, to_version) = migrate_value(filename, original.clone())
+        .with_context(|| format!("Failed to migrate config file: {}", path.display()))?;
+
+    let mut report = MigrationReport {
+        path: path.to_path_buf(),
+        from_version,
+        to_version,
+        diff: String::new(),
+        backup: None,
+        written: false,
+    };
+    if from_version == to_version {
+        return Ok(report);
+    }
+
+    let before = to_pretty_json(original)?;
+    let after = to_pretty_json(&migrated)?;
+    report.diff = similar::TextDiff::from_lines(&before, &after)
+        .unified_diff()
+        .header(&format!("{} (v{})", path.display(), from_version), &format!("{} (v{})", path.display(), to_version))
+        .to_string();
+
+    if dry_run {
+        return Ok(report);
+    }
+
+    report.backup = Some(backup_file(path, backup_dir)?);
+
+    // Keep the user's permissions rather than the temp file's, and make the
+    // contents durable before the rename replaces the original
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let permissions = fs::metadata(path)?.permissions();
+    let mut temp_file = NamedTempFile::new_in(dir)?;
+    temp_file.write_all(after.as_bytes())?;
+    temp_file.as_file().set_permissions(permissions)?;
+    temp_file.as_file().sync_all()?;
+    temp_file.persist(path)?;
+    report.written = true;
+
+    log::info!(
+        "Migrated {} from version {} to {}",
+        path.display(),
+        from_version,
+        to_version
+    );
+    Ok(report)
+}
+
+/// Copy a file to `{backup_dir}/{filename}.{timestamp}.bak`
+fn backup_file(path: &Path, backup_dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(backup_dir)
+        .with_context(|| format!("Failed to create backup directory: {}", backup_dir.display()))?;
+
+    let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("config");
+    let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ");
+    let backup = backup_dir.join(format!("{}.{}.bak", filename, timestamp));
+    fs::copy(path, &backup)
+        .with_context(|| format!("Failed to back up {} to {}", path.display(), backup.display()))?;
+    Ok(backup)
+}
+
+fn to_pretty_json(value: &Value) -> Result<String> {
+    let mut text = serde_json::to_string_pretty(value)?;
+    text.push('\n');
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rename_listen(mut value: Value) -> Result<Value> {
+        if let Some(map) = value.as_object_mut()
+            && let Some(listen) = map.remove("listen")
+        {
+            map.insert("bind".to_string(), listen);
+        }
+        Ok(value)
+    }
+
+    fn add_timeout(mut value: Value) -> Result<Value> {
+        if let Some(map) = value.as_object_mut() {
+            map.entry("timeout_ms").or_insert(json!(5000));
+        }
+        Ok(value)
+    }
+
+    #[test]
+    fn test_migrations_run_in_sequence() {
+        register_migration("test_seq.json", 1, rename_listen);
+        register_migration("test_seq.json", 2, add_timeout);
+        assert_eq!(current_version("test_seq.json"), 3);
+
+        let (value, from, to) = migrate_value("test_seq.json", json!({ "listen": "127.0.0.1" })).unwrap();
+        assert_eq!((from, to), (1, 3));
+        assert_eq!(value, json!({ "version": 3, "bind": "127.0.0.1", "timeout_ms": 5000 }));
+
+        // Already current: untouched
+        let (same, from, to) = migrate_value("test_seq.json", value.clone()).unwrap();
+        assert_eq!((same, from, to), (value, 3, 3));
+    }
+
+    #[test]
+    fn test_unversioned_files_are_untouched() {
+        let value = json!({ "version": "0.1.0", "name": "x" });
+        let (same, from, to) = migrate_value("test_unversioned.json", value.clone()).unwrap();
+        assert_eq!((same, from, to), (value, 1, 1));
+    }
+
+    #[test]
+    fn test_newer_version_and_missing_step_are_errors() {
+        register_migration("test_gap.json", 2, add_timeout);
+
+        let err = migrate_value("test_gap.json", json!({ "version": 9 })).unwrap_err();
+        assert!(err.to_string().contains("newer"), "{}", err);
+
+        let err = migrate_value("test_gap.json", json!({})).unwrap_err();
+        assert!(err.to_string().contains("from version 1 to 2"), "{}", err);
+    }
+
+    #[test]
+    fn test_dry_run_and_write_back() {
+        register_migration("test_file.json", 1, rename_listen);
+
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("test_file.json");
+        let backups = temp.path().join("backups");
+        let original = json!({ "listen": "0.0.0.0" });
+        fs::write(&path, to_pretty_json(&original).unwrap()).unwrap();
+
+        let report = migrate_file(&path, &original, &backups, true).unwrap();
+        assert!(report.is_migrated() && !report.written);
+        assert!(report.diff.contains("-  \"listen\": \"0.0.0.0\""), "{}", report.diff);
+        assert!(report.diff.contains("+  \"bind\": \"0.0.0.0\","), "{}", report.diff);
+        assert!(!backups.exists());
+
+        let report = migrate_file(&path, &original, &backups, false).unwrap();
+        assert!(report.written);
+        let backup = report.backup.unwrap();
+        assert_eq!(fs::read_to_string(backup).unwrap(), to_pretty_json(&original).unwrap());
+
+        let written: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written, json!({ "version": 2, "bind": "0.0.0.0" }));
+
+        // The rewritten file keeps the original permissions
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::write(&path, to_pretty_json(&original).unwrap()).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+            migrate_file(&path, &original, &backups, false).unwrap();
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o644);
+        }
+
+        // Key order of the user's file is kept
+        let original = json!({ "zeta": 1, "listen": "0.0.0.0", "alpha": 2 });
+        fs::write(&path, to_pretty_json(&original).unwrap()).unwrap();
+        migrate_file(&path, &original, &backups, false).unwrap();
+        let written: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let keys: Vec<&str> = written.as_object().unwrap().keys().map(String::as_str).collect();
+        assert_eq!(keys, ["zeta", "alpha", "bind", "version"]);
+    }
+}
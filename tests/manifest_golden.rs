@@ -0,0 +1,26 @@
+//! Golden test for the registry manifest
+//!
+//! Fails on any change to categories, tools, ports or header names. If the
+//! change is intended, regenerate the golden file and review the diff:
+//!
+//! `KODEGEN_UPDATE_GOLDEN=1 cargo test --test manifest_golden`
+
+use std::path::Path;
+
+#[test]
+fn test_manifest_matches_golden() {
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/manifest.json");
+    let actual = serde_json::to_string_pretty(&kodegen_config::registry_manifest()).unwrap() + "\n";
+
+    if std::env::var_os("KODEGEN_UPDATE_GOLDEN").is_some() {
+        std::fs::write(&golden, &actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&golden).expect("missing tests/golden/manifest.json");
+    assert!(
+        actual == expected,
+        "Manifest changed. If intended, run `KODEGEN_UPDATE_GOLDEN=1 cargo test --test manifest_golden`\n\
+         and bump MANIFEST_VERSION if a field was removed or changed meaning."
+    );
+}